@@ -0,0 +1,15 @@
+use visualizer_shapes::*;
+
+fn main() {
+    let mut frames = Frames::new();
+    let mut layout = GraphLayout::new(Layout::ForceDirected);
+    let mut graph = Graph::new();
+    for i in 0..20 {
+        graph = graph.add_node(Node::new().label(i.to_string()).color(Color::tag(i % 3)).with_msg(format!("node {}", i)));
+        if i > 0 {
+            graph = graph.add_edge(Edge::new((i * 7) % i, i).directed().weight(i as f32).with_msg(format!("edge {}", i)));
+        }
+        frames = frames.add_frame(Frame::auto_bounds().margin(10.0).add_graph(&graph, &mut layout));
+    }
+    frames.encode_to_file("visualizer/graph.vis").unwrap();
+}
@@ -0,0 +1,26 @@
+use visualizer_shapes::*;
+
+fn main() {
+    let mut frames = Frames::new();
+    let lattices = [
+        Lattice::hex_offset(pos(0.0, 0.0), 10.0, true, true),
+        Lattice::triangular(pos(0.0, 0.0), 20.0),
+        Lattice::isometric(pos(0.0, 0.0), 30.0),
+    ];
+    for lattice in lattices {
+        let mut grid = Grid::rect(lattice, 8, 6);
+        for (k, cell) in grid.cells.iter_mut().enumerate() {
+            if k % 3 == 0 {
+                cell.fill = Some(Color::tag(k % 5));
+                cell.msg = Some(format!("cell {}", k));
+            }
+        }
+        let center = lattice.center(4, 3);
+        frames = frames.add_frame(Frame::auto_bounds().margin(10.0)
+            .add_element(grid.element().with_msg(format!("{:?}", lattice.kind)))
+            .add_element(Circle::new(center, 3.0).fill(Color::new(255, 0, 0)).element().with_msg(format!("{:?}", lattice.locate(center)))));
+    }
+    let board = Grid::hexagon(Lattice::hex_axial(pos(0.0, 0.0), 10.0, false), 4).stroke(Color::new(80, 80, 80), 2.0);
+    frames = frames.add_frame(Frame::auto_bounds().margin(10.0).add_element(board.element()));
+    frames.encode_to_file("visualizer/lattice.vis").unwrap();
+}
@@ -0,0 +1,17 @@
+use visualizer_shapes::*;
+
+// Start the viewer, press "listen" in its live section, then run this example.
+fn main() -> anyhow::Result<()> {
+    let mut client = LiveClient::connect(live::Endpoint::DEFAULT)?;
+    let mut x = 50.0f32;
+    for i in 0..300u64 {
+        x = (x + (i % 7) as f32 * 3.0) % 100.0;
+        let frame = Frame::new(pos(0.0, 0.0), pos(100.0, 100.0))
+            .step(i)
+            .add_element(Circle::new(pos(x, (i % 100) as f32), 3.0).fill(Color::tag(i as usize % 5)).element().with_msg(format!("i = {}", i)));
+        client.send_frame(frame)?;
+        client.send_sample("x", i, x as f64)?;
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use visualizer_shapes::*;
+
+fn main() {
+    let mut x = 50.0f32;
+    for i in 0..100 {
+        vis_frame!(pos(0.0, 0.0), pos(100.0, 100.0));
+        vis_elem!(Legend::colormap(Colormap::Turbo, "0", "100").title("x").element());
+        vis_elem!(Legend::tags((0..5).map(|k| format!("i % 5 = {}", k))).corner(Corner::TopRight).element());
+        let next = (x + (i % 7) as f32 * 3.0) % 100.0;
+        if next < x {
+            vis_event!("wrapped around");
+        }
+        x = next;
+        vis_circle!(pos(x, i as f32), 3.0, fill(Color::tag(i % 5)), stroke(Color::new(0, 0, 0), 1.0));
+        vis_msg!("i = {}, x = {}", i, x);
+        vis_attr!("x", x);
+        vis_id!(i);
+        vis_layer!("points");
+        vis_series!("x", x);
+        vis_path!([pos(0.0, i as f32), pos(x, i as f32)], stroke(Color::turbo(x / 100.0), 1.0));
+        vis_layer!("bars");
+    }
+    vis_flush!("visualizer/recorder.vis");
+}
@@ -0,0 +1,26 @@
+use visualizer_shapes::*;
+
+fn main() {
+    let recorder = TrackRecorder::new();
+    std::thread::scope(|s| {
+        for w in 0..4 {
+            let recorder = &recorder;
+            std::thread::Builder::new().name(format!("worker {}", w)).spawn_scoped(s, move || {
+                let mut x = 50.0f32;
+                for i in 0..100 {
+                    x = (x + ((i * (w + 1)) % 7) as f32 * 3.0) % 100.0;
+                    recorder.add_frame(
+                        Frame::new(pos(0.0, 0.0), pos(100.0, 100.0))
+                            .add_element(
+                                Circle::new(pos(x, i as f32), 5.0)
+                                    .fill(Color::tag(w))
+                                    .element()
+                                    .with_msg(format!("worker {}, x = {}", w, x))
+                            )
+                    );
+                }
+            }).unwrap();
+        }
+    });
+    recorder.encode_to_file("visualizer/tracks.vis").unwrap();
+}
@@ -0,0 +1,191 @@
+use crate::*;
+
+/// Rough width of one character relative to the font size, used to estimate text extents.
+const CHAR_WIDTH: f32 = 0.6;
+
+fn union(a: Option<(Pos, Pos)>, b: Option<(Pos, Pos)>) -> Option<(Pos, Pos)> {
+    match (a, b) {
+        (Some((a1, a2)), Some((b1, b2))) => Some((
+            pos(a1.x.min(b1.x), a1.y.min(b1.y)),
+            pos(a2.x.max(b2.x), a2.y.max(b2.y)),
+        )),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn around(c: Pos, hw: f32, hh: f32) -> Option<(Pos, Pos)> {
+    if c.x.is_finite() && c.y.is_finite() && hw.is_finite() && hh.is_finite() {
+        Some((pos(c.x - hw, c.y - hh), pos(c.x + hw, c.y + hh)))
+    }
+    else {
+        None
+    }
+}
+
+impl Element {
+    /// The axis-aligned box `(min, max)` covered by this element, including the stroke width.
+    /// Non-finite coordinates are ignored.
+    pub fn extent(&self) -> Option<(Pos, Pos)> {
+        match self.shape {
+            Shape::Path(ref p) => {
+                let w = p.stroke.width.max(0.0) / 2.0;
+                p.vp.iter().fold(None, |acc, &v| union(acc, around(v, w, w)))
+            }
+            Shape::Circle(ref c) => {
+                let w = c.stroke.as_ref().map_or(0.0, |s| s.width.max(0.0) / 2.0);
+                let r = c.radius.abs() + w;
+                around(c.center, r, r)
+            }
+            Shape::Text(ref t) => {
+                let chars = t.text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+                let lines = t.text.lines().count().max(1);
+                around(t.pos, chars as f32 * t.size * CHAR_WIDTH / 2.0, lines as f32 * t.size / 2.0)
+            }
+            Shape::Grid(ref g) => {
+                let w = g.stroke.width.max(0.0) / 2.0;
+                g.cells.iter()
+                    .flat_map(|c| g.lattice.vertices(c.index.0, c.index.1))
+                    .fold(None, |acc, v| union(acc, around(v, w, w)))
+            }
+            // drawn in screen space, so it takes no room in the frame
+            Shape::Legend(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AutoBounds {
+    margin: f32,
+    extent: Option<(Pos, Pos)>,
+}
+
+impl Frame {
+    /// Creates a frame whose bounds follow the extents of its elements.
+    pub fn auto_bounds() -> Self {
+        Self {
+            auto: Some(AutoBounds { margin: 0.0, extent: None }),
+            ..Self::new(pos(0.0, 0.0), pos(0.0, 0.0))
+        }
+    }
+
+    /// Adds `margin` on every side of the bounds. An `auto_bounds` frame keeps the margin
+    /// around the element extents as more elements are added.
+    pub fn margin(mut self, margin: f32) -> Self {
+        match self.auto {
+            Some(ref mut auto) => {
+                auto.margin += margin;
+                let (extent, margin) = (auto.extent, auto.margin);
+                self.set_bounds(extent, margin);
+            }
+            None => {
+                // p1 is not necessarily the smaller corner, so grow away from p2 on each axis
+                let widen = |a: f32, b: f32| if a <= b { (a - margin, b + margin) } else { (a + margin, b - margin) };
+                (self.p1.x, self.p2.x) = widen(self.p1.x, self.p2.x);
+                (self.p1.y, self.p2.y) = widen(self.p1.y, self.p2.y);
+            }
+        }
+        self
+    }
+
+    /// The union of the extents of all elements in this frame.
+    pub fn extent(&self) -> Option<(Pos, Pos)> {
+        self.elems.iter().fold(None, |acc, e| union(acc, e.extent()))
+    }
+
+    fn set_bounds(&mut self, extent: Option<(Pos, Pos)>, margin: f32) {
+        if let Some((p1, p2)) = extent {
+            self.p1 = pos(p1.x - margin, p1.y - margin);
+            self.p2 = pos(p2.x + margin, p2.y + margin);
+        }
+    }
+
+    pub(crate) fn grow_bounds(&mut self, elem: &Element) {
+        if let Some(ref mut auto) = self.auto {
+            auto.extent = union(auto.extent, elem.extent());
+            let (extent, margin) = (auto.extent, auto.margin);
+            self.set_bounds(extent, margin);
+        }
+    }
+}
+
+impl Frames {
+    /// The union of the element extents of all frames.
+    pub fn extent(&self) -> Option<(Pos, Pos)> {
+        self.all_frames().fold(None, |acc, f| union(acc, f.extent()))
+    }
+
+    /// Sets the bounds of every frame to the union of all frames, so the view does not
+    /// jump while scrubbing.
+    pub fn union_bounds(mut self, margin: f32) -> Self {
+        let extent = self.extent();
+        for frame in self.frames.iter_mut().chain(self.tracks.iter_mut().flat_map(|t| t.frames.iter_mut())) {
+            frame.set_bounds(extent, margin);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(frame: &Frame) -> (Pos, Pos) {
+        (frame.p1, frame.p2)
+    }
+
+    #[test]
+    fn empty_frames_have_no_extent() {
+        let frame = Frame::auto_bounds().margin(1.0);
+        assert_eq!(frame.extent(), None);
+        assert_eq!(bounds(&frame), (pos(0.0, 0.0), pos(0.0, 0.0)));
+        let frames = Frames::new().add_frame(Frame::new(pos(0.0, 0.0), pos(5.0, 5.0))).union_bounds(1.0);
+        assert_eq!(frames.extent(), None);
+        assert_eq!(bounds(&frames.frames[0]), (pos(0.0, 0.0), pos(5.0, 5.0)));
+    }
+
+    #[test]
+    fn text_extents_are_estimated_from_the_char_count() {
+        let text = Text::new("abcd", 2.0, pos(10.0, 10.0)).element();
+        let hw = 4.0 * 2.0 * CHAR_WIDTH / 2.0;
+        assert_eq!(text.extent(), Some((pos(10.0 - hw, 9.0), pos(10.0 + hw, 11.0))));
+        // the longest line counts, and every line adds the font size
+        let text = Text::new("ab\ncdef", 2.0, pos(10.0, 10.0)).element();
+        assert_eq!(text.extent(), Some((pos(10.0 - hw, 8.0), pos(10.0 + hw, 12.0))));
+    }
+
+    #[test]
+    fn extents_include_the_stroke_and_skip_non_finite_coordinates() {
+        let path = Path::from_vertices(vec![pos(0.0, 0.0), pos(f32::NAN, 1.0), pos(4.0, 2.0)]).stroke(Color::new(0, 0, 0), 2.0);
+        assert_eq!(path.element().extent(), Some((pos(-1.0, -1.0), pos(5.0, 3.0))));
+        let circle = Circle::new(pos(1.0, 1.0), 2.0).stroke(Color::new(0, 0, 0), 1.0);
+        assert_eq!(circle.element().extent(), Some((pos(-1.5, -1.5), pos(3.5, 3.5))));
+    }
+
+    #[test]
+    fn auto_bounds_keep_the_margin_around_new_elements() {
+        let frame = Frame::auto_bounds()
+            .margin(1.0)
+            .add_element(Circle::new(pos(0.0, 0.0), 1.0).element())
+            .add_element(Circle::new(pos(4.0, 0.0), 1.0).element());
+        assert_eq!(bounds(&frame), (pos(-2.0, -2.0), pos(6.0, 2.0)));
+        assert_eq!(bounds(&frame.margin(1.0)), (pos(-3.0, -3.0), pos(7.0, 3.0)));
+    }
+
+    #[test]
+    fn fixed_bounds_grow_away_from_the_other_corner() {
+        assert_eq!(bounds(&Frame::new(pos(0.0, 2.0), pos(2.0, 0.0)).margin(1.0)), (pos(-1.0, 3.0), pos(3.0, -1.0)));
+    }
+
+    #[test]
+    fn union_bounds_cover_every_track() {
+        let circle = |x: f32| Frame::new(pos(0.0, 0.0), pos(1.0, 1.0)).add_element(Circle::new(pos(x, 0.0), 1.0).element());
+        let frames = Frames::new()
+            .add_frame(circle(0.0))
+            .add_track(Track::new("t").add_frame(circle(10.0)))
+            .union_bounds(0.5);
+        for frame in frames.all_frames() {
+            assert_eq!(bounds(frame), (pos(-1.5, -1.5), pos(11.5, 1.5)));
+        }
+    }
+}
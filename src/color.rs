@@ -0,0 +1,167 @@
+//! More ways to build a `Color`, and the colormaps shared with the viewer.
+
+use serde::{ Serialize, Deserialize };
+use crate::*;
+
+fn from_colorous(c: colorous::Color) -> Color {
+    Color::new(c.r, c.g, c.b)
+}
+
+/// A colormap from `[0, 1]` (or a category index) to a color.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    #[default]
+    Turbo,
+    Viridis,
+    Magma,
+    Plasma,
+    Cividis,
+    /// Diverging, red at 0 and blue at 1.
+    RdBu,
+    /// Categorical, ten colors.
+    Tableau10,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 7] = [
+        Colormap::Turbo, Colormap::Viridis, Colormap::Magma, Colormap::Plasma,
+        Colormap::Cividis, Colormap::RdBu, Colormap::Tableau10,
+    ];
+
+    /// The lowercase name, as written in the text color syntax.
+    pub fn name(self) -> &'static str {
+        match self {
+            Colormap::Turbo => "turbo",
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Plasma => "plasma",
+            Colormap::Cividis => "cividis",
+            Colormap::RdBu => "rdbu",
+            Colormap::Tableau10 => "tableau10",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn is_categorical(self) -> bool {
+        self == Colormap::Tableau10
+    }
+
+    fn gradient(self) -> Option<colorous::Gradient> {
+        match self {
+            Colormap::Turbo => Some(colorous::TURBO),
+            Colormap::Viridis => Some(colorous::VIRIDIS),
+            Colormap::Magma => Some(colorous::MAGMA),
+            Colormap::Plasma => Some(colorous::PLASMA),
+            Colormap::Cividis => Some(colorous::CIVIDIS),
+            Colormap::RdBu => Some(colorous::RED_BLUE),
+            Colormap::Tableau10 => None,
+        }
+    }
+
+    /// The color at `t`, clamped to `[0, 1]`. A categorical map splits the range into
+    /// equal buckets. NaN maps to 0.
+    pub fn eval(self, t: f32) -> Color {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        match self.gradient() {
+            Some(g) => from_colorous(g.eval_continuous(t as f64)),
+            None => {
+                let n = colorous::TABLEAU10.len();
+                from_colorous(colorous::TABLEAU10[((t * n as f32) as usize).min(n - 1)])
+            }
+        }
+    }
+
+    /// The `i`-th of `n` evenly spaced colors, from the first to the last color of the map.
+    /// A categorical map ignores `n` and cycles through its colors.
+    pub fn eval_discrete(self, i: usize, n: usize) -> Color {
+        match self.gradient() {
+            Some(g) => from_colorous(g.eval_rational(i, n.max(1))),
+            None => from_colorous(colorous::TABLEAU10[i % colorous::TABLEAU10.len()]),
+        }
+    }
+}
+
+fn hex_digits(s: &str) -> Option<Vec<u8>> {
+    s.chars().map(|c| c.to_digit(16).map(|d| d as u8)).collect()
+}
+
+impl Color {
+    /// Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`. The `#` is optional.
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let d = hex_digits(s.trim().trim_start_matches('#'))?;
+        match d.len() {
+            3 | 4 => {
+                let c = |i: usize| d.get(i).map_or(255, |&v| v * 17);
+                Some(Self::newa(c(0), c(1), c(2), c(3)))
+            }
+            6 | 8 => {
+                let c = |i: usize| if 2 * i < d.len() { d[2 * i] * 16 + d[2 * i + 1] } else { 255 };
+                Some(Self::newa(c(0), c(1), c(2), c(3)))
+            }
+            _ => None,
+        }
+    }
+    /// A CSS color name such as `teal`, ignoring case.
+    pub fn named(name: &str) -> Option<Self> {
+        if name.trim().is_empty() || !name.is_ascii() {
+            return None;
+        }
+        let [r, g, b] = color_name::css::Color::val().by_string(name.to_owned()).ok()?;
+        Some(Self::new(r, g, b))
+    }
+    /// `h` is in turns, `s` and `v` in `[0, 1]`.
+    pub fn hsv(h: f32, s: f32, v: f32) -> Self {
+        Self::hsva(h, s, v, 1.0)
+    }
+    pub fn hsva(h: f32, s: f32, v: f32, a: f32) -> Self {
+        let [r, g, b] = rgb_from_hsv((h, s, v.clamp(0.0, 1.0)));
+        Self::ratioa(r, g, b, a.clamp(0.0, 1.0))
+    }
+    /// `h` is in turns, `s` and `l` in `[0, 1]`.
+    pub fn hsl(h: f32, s: f32, l: f32) -> Self {
+        Self::hsla(h, s, l, 1.0)
+    }
+    pub fn hsla(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        let v = l + s * l.min(1.0 - l);
+        let sv = if v > 0.0 { 2.0 * (1.0 - l / v) } else { 0.0 };
+        Self::hsva(h, sv, v, a)
+    }
+    /// Interpolates every channel, alpha included, with `t` clamped to `[0, 1]`.
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let l = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Self::newa(l(self.r, other.r), l(self.g, other.g), l(self.b, other.b), l(self.a, other.a))
+    }
+    /// `top` painted over `self`.
+    pub fn blend(self, top: Color) -> Color {
+        let (ta, ba) = (top.a as f32 / 255.0, self.a as f32 / 255.0);
+        let a = ta + ba * (1.0 - ta);
+        if a <= 0.0 {
+            return Self::newa(0, 0, 0, 0);
+        }
+        let c = |t: u8, b: u8| ((t as f32 * ta + b as f32 * ba * (1.0 - ta)) / a).round() as u8;
+        Self::newa(c(top.r, self.r), c(top.g, self.g), c(top.b, self.b), (a * 255.0).round() as u8)
+    }
+    pub fn viridis(x: f32) -> Self {
+        Colormap::Viridis.eval(x)
+    }
+    pub fn magma(x: f32) -> Self {
+        Colormap::Magma.eval(x)
+    }
+    pub fn plasma(x: f32) -> Self {
+        Colormap::Plasma.eval(x)
+    }
+    pub fn cividis(x: f32) -> Self {
+        Colormap::Cividis.eval(x)
+    }
+    pub fn rdbu(x: f32) -> Self {
+        Colormap::RdBu.eval(x)
+    }
+    pub fn tableau10(idx: usize) -> Self {
+        Colormap::Tableau10.eval_discrete(idx, 10)
+    }
+}
@@ -0,0 +1,477 @@
+//! The binary encoding of `Frames`, shared by files, pipes and the live socket.
+//!
+//! An encoding starts with `MAGIC` and a little-endian `u32` version, followed by records:
+//! a little-endian `u64` length and the bincode of a `Packet`. Records of a kind this version
+//! does not know are skipped.
+//!
+//! A frame is written in the baseline layout, its elements with their shape and msg followed
+//! by `p1` and `p2`, and then a length-prefixed `FrameExt` with everything added since. Fields
+//! are only ever appended to `FrameExt`: a reader fills the ones missing from an older file with
+//! their default and ignores the ones it does not know.
+//!
+//! Data without the magic is the baseline encoding, a bare bincode `Frames` with only `frames`.
+
+use serde::{ Serialize, Deserialize, Serializer, Deserializer };
+use serde::de::{ DeserializeOwned, DeserializeSeed, SeqAccess, Visitor };
+use std::borrow::{ Borrow, Cow };
+use std::io::{ Read, Write };
+use anyhow::bail;
+use bincode::Options as _;
+use crate::*;
+
+/// Can not start a baseline encoding, whose first 8 bytes are the frame count.
+pub const MAGIC: [u8; 4] = *b"\x89VIS";
+pub const VERSION: u32 = 1;
+
+/// Packets larger than this are rejected as garbage rather than allocated.
+const MAX_PACKET: u64 = 1 << 30;
+
+/// The number of `Packet` variants, to skip records written by a newer version.
+const PACKET_KINDS: u32 = 3;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Packet {
+    /// A frame for the main track, or for the named track.
+    Frame {
+        track: Option<String>,
+        #[serde(with = "frame")]
+        frame: Frame,
+    },
+    Sample { series: String, step: u64, value: f64 },
+    /// A whole series, appended to the samples already received.
+    Series(Series),
+}
+
+/// A `Packet` borrowing its contents, encoded the same way.
+#[derive(Serialize)]
+#[serde(rename = "Packet")]
+pub(crate) enum PacketRef<'a> {
+    Frame {
+        track: Option<&'a str>,
+        #[serde(with = "frame")]
+        frame: &'a Frame,
+    },
+    Sample { series: &'a str, step: u64, value: f64 },
+    Series(&'a Series),
+}
+
+/// An element in the baseline layout.
+#[derive(Deserialize)]
+struct BaseElement {
+    shape: Shape,
+    msg: Option<String>,
+}
+
+/// A frame in the baseline layout.
+#[derive(Deserialize)]
+struct BaseFrame {
+    elems: Vec<BaseElement>,
+    p1: Pos,
+    p2: Pos,
+}
+
+/// Deserializes the baseline `Frames`, handing every frame to the callback as soon as it is
+/// decoded rather than collecting them.
+struct EachBaseFrame<'f, F>(&'f mut F);
+
+impl<'de, 'f, F: FnMut(BaseFrame)> DeserializeSeed<'de> for EachBaseFrame<'f, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+        d.deserialize_struct("Frames", &["frames"], self)
+    }
+}
+
+impl<'de, 'f, F: FnMut(BaseFrame)> Visitor<'de> for EachBaseFrame<'f, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a sequence of frames")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        // the struct `Frames` has one field, and that is a sequence of frames
+        if let Some(frames) = seq.next_element_seed(BaseFrameSeq(self.0))? {
+            return Ok(frames);
+        }
+        Err(serde::de::Error::invalid_length(0, &self))
+    }
+}
+
+/// The field `frames` of `EachBaseFrame`.
+struct BaseFrameSeq<'f, F>(&'f mut F);
+
+impl<'de, 'f, F: FnMut(BaseFrame)> DeserializeSeed<'de> for BaseFrameSeq<'f, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+        d.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'f, F: FnMut(BaseFrame)> Visitor<'de> for BaseFrameSeq<'f, F> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a sequence of frames")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(frame) = seq.next_element()? {
+            (self.0)(frame);
+        }
+        Ok(())
+    }
+}
+
+/// What a frame carries besides the baseline layout. Append new fields at the end.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct FrameExt<'a> {
+    keyframe: bool,
+    step: Option<u64>,
+    events: Cow<'a, [String]>,
+    /// One entry per element, or none when no element has any.
+    attrs: Vec<Cow<'a, [(String, f32)]>>,
+    layers: Vec<Option<Cow<'a, str>>>,
+    ids: Vec<Option<u64>>,
+}
+
+/// `column` with one value per element, or nothing when every value is the default.
+fn column<'a, T: Default + PartialEq>(elems: &'a [Element], f: impl Fn(&'a Element) -> T) -> Vec<T> {
+    if elems.iter().all(|e| f(e) == T::default()) {
+        vec![]
+    }
+    else {
+        elems.iter().map(f).collect()
+    }
+}
+
+impl<'a> FrameExt<'a> {
+    fn of(frame: &'a Frame) -> Self {
+        Self {
+            keyframe: frame.keyframe,
+            step: frame.step,
+            events: Cow::Borrowed(&frame.events),
+            attrs: column(&frame.elems, |e| Cow::Borrowed(&e.attrs[..])),
+            layers: column(&frame.elems, |e| e.layer.as_deref().map(Cow::Borrowed)),
+            ids: column(&frame.elems, |e| e.id),
+        }
+    }
+}
+
+fn base_frame(base: BaseFrame) -> Frame {
+    let elems = base.elems.into_iter().map(|e| Element { msg: e.msg, ..Element::new(e.shape) }).collect();
+    Frame::from_elements(base.p1, base.p2, elems)
+}
+
+fn bincode_options() -> impl bincode::Options {
+    // the options of `bincode::serialize`
+    bincode::DefaultOptions::new().with_fixint_encoding().allow_trailing_bytes()
+}
+
+/// Deserializes the fields of a struct from `bytes` one by one, leaving the fields past
+/// the end of an older encoding to `#[serde(default)]`.
+struct Appended<'b>(&'b [u8]);
+
+impl<'de, 'b> Deserializer<'de> for Appended<'b> {
+    type Error = bincode::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> bincode::Result<V::Value> {
+        Err(serde::de::Error::custom("only structs can be appended to"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _: &'static str, fields: &'static [&'static str], visitor: V) -> bincode::Result<V::Value> {
+        visitor.visit_seq(AppendedFields { rest: self.0, left: fields.len() })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct AppendedFields<'b> {
+    rest: &'b [u8],
+    left: usize,
+}
+
+impl<'de, 'b> SeqAccess<'de> for AppendedFields<'b> {
+    type Error = bincode::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> bincode::Result<Option<T::Value>> {
+        if self.left == 0 || self.rest.is_empty() {
+            return Ok(None);
+        }
+        self.left -= 1;
+        let mut de = bincode::Deserializer::with_reader(&mut self.rest, bincode_options());
+        seed.deserialize(&mut de).map(Some)
+    }
+}
+
+fn from_appended<T: DeserializeOwned>(bytes: &[u8]) -> bincode::Result<T> {
+    T::deserialize(Appended(bytes))
+}
+
+/// `#[serde(with = "frame")]`: the baseline layout and a `FrameExt` in binary formats,
+/// and the plain `Frame` in human-readable ones like JSON.
+pub(crate) mod frame {
+    use super::*;
+
+    pub fn serialize<F: Borrow<Frame>, S: Serializer>(frame: &F, s: S) -> Result<S::Ok, S::Error> {
+        let frame = frame.borrow();
+        if s.is_human_readable() {
+            return frame.serialize(s);
+        }
+        // a tuple is encoded like the struct with the same fields
+        let elems = frame.elems.iter().map(|e| (&e.shape, &e.msg)).collect::<Vec<_>>();
+        let ext = bincode::serialize(&FrameExt::of(frame)).map_err(serde::ser::Error::custom)?;
+        (elems, frame.p1, frame.p2, ext).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Frame, D::Error> {
+        if d.is_human_readable() {
+            return Frame::deserialize(d);
+        }
+        let (elems, p1, p2, ext): (Vec<BaseElement>, Pos, Pos, Vec<u8>) = Deserialize::deserialize(d)?;
+        let ext: FrameExt<'static> = from_appended(&ext).map_err(serde::de::Error::custom)?;
+        let mut frame = base_frame(BaseFrame { elems, p1, p2 });
+        frame.keyframe = ext.keyframe;
+        frame.step = ext.step;
+        frame.events = ext.events.into_owned();
+        for (i, e) in frame.elems.iter_mut().enumerate() {
+            e.attrs = ext.attrs.get(i).map_or(vec![], |a| a.to_vec());
+            e.layer = ext.layers.get(i).cloned().flatten().map(Cow::into_owned);
+            e.id = ext.ids.get(i).copied().flatten();
+        }
+        Ok(frame)
+    }
+}
+
+pub(crate) fn write_record<W: Write, P: Serialize>(writer: &mut W, packet: &P) -> anyhow::Result<()> {
+    let len = bincode::serialized_size(packet)?;
+    writer.write_all(&len.to_le_bytes())?;
+    bincode::serialize_into(writer, packet)?;
+    Ok(())
+}
+
+pub fn write_header<W: Write>(writer: &mut W) -> anyhow::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    Ok(())
+}
+
+pub fn write_packet<W: Write>(writer: &mut W, packet: &Packet) -> anyhow::Result<()> {
+    write_record(writer, packet)
+}
+
+/// Writes the header and a record for every frame and every series.
+pub fn write_frames<W: Write>(writer: &mut W, frames: &Frames) -> anyhow::Result<()> {
+    write_header(writer)?;
+    for frame in &frames.frames {
+        write_record(writer, &PacketRef::Frame { track: None, frame })?;
+    }
+    for track in &frames.tracks {
+        for frame in &track.frames {
+            write_record(writer, &PacketRef::Frame { track: Some(&track.name), frame })?;
+        }
+    }
+    for series in &frames.series {
+        write_record(writer, &PacketRef::Series(series))?;
+    }
+    Ok(())
+}
+
+/// Reads the next packet, or `None` if the stream ended between packets.
+pub fn read_packet<R: Read>(reader: &mut R) -> anyhow::Result<Option<Packet>> {
+    loop {
+        let mut len = [0u8; 8];
+        match read_up_to(reader, &mut len)? {
+            0 => return Ok(None),
+            8 => {}
+            n => bail!("the stream ends {} bytes into the length of a packet", n),
+        }
+        let len = u64::from_le_bytes(len);
+        if len > MAX_PACKET {
+            bail!("packet of {} bytes is too large, the stream is probably not a visualizer_shapes encoding", len);
+        }
+        // grows with what arrives instead of trusting the length up front
+        let mut buf = vec![];
+        let n = reader.by_ref().take(len).read_to_end(&mut buf)?;
+        if (n as u64) < len {
+            bail!("the stream ends {} bytes into a packet of {} bytes", n, len);
+        }
+        // bincode starts an enum with its variant index
+        match buf.get(..4) {
+            Some(kind) if u32::from_le_bytes(kind.try_into().unwrap()) >= PACKET_KINDS => continue,
+            _ => return Ok(Some(bincode::deserialize(&buf)?)),
+        }
+    }
+}
+
+/// Fills `buf` as far as the reader goes, returning how much was read.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+/// Reads an encoding, handing every packet to `f` as soon as it is decoded, so frames can be
+/// shown while a pipe or a socket is still being written.
+pub fn read_packets<R: Read>(mut reader: R, mut f: impl FnMut(Packet)) -> anyhow::Result<()> {
+    let mut magic = [0u8; 4];
+    let n = read_up_to(&mut reader, &mut magic)?;
+    if magic[..n] != MAGIC {
+        let mut de = bincode::Deserializer::with_reader((&magic[..n]).chain(reader), bincode_options());
+        let mut each = |frame| f(Packet::Frame { track: None, frame: base_frame(frame) });
+        EachBaseFrame(&mut each).deserialize(&mut de)?;
+        return Ok(());
+    }
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version > VERSION {
+        bail!("encoded by a newer version {} of visualizer_shapes, this one reads up to version {}", version, VERSION);
+    }
+    while let Some(packet) = read_packet(&mut reader)? {
+        f(packet);
+    }
+    Ok(())
+}
+
+impl Frames {
+    /// Adds a decoded packet: a frame to its track, or samples to their series.
+    pub fn push_packet(&mut self, packet: Packet) {
+        match packet {
+            Packet::Frame { track: None, frame } => self.frames.push(frame),
+            Packet::Frame { track: Some(name), frame } => match self.tracks.iter_mut().find(|t| t.name == name) {
+                Some(track) => track.frames.push(frame),
+                None => self.tracks.push(Track::new(name).add_frame(frame)),
+            },
+            Packet::Sample { series, step, value } => self.push_sample(&series, step, value),
+            Packet::Series(series) => match self.series.iter_mut().find(|s| s.name == series.name) {
+                Some(s) => s.samples.extend(series.samples),
+                None => self.series.push(series),
+            },
+        }
+    }
+
+    pub fn encode_to_writer<W: Write>(&self, mut writer: W) -> anyhow::Result<()> {
+        write_frames(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn decode_from_reader<R: Read>(reader: R) -> anyhow::Result<Self> {
+        let mut frames = Frames::new();
+        read_packets(reader, |p| frames.push_packet(p))?;
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_the_baseline_encoding() {
+        let frames = Frames::decode(include_bytes!("../tests/data/baseline.vis").to_vec()).unwrap();
+        assert!(frames.tracks.is_empty() && frames.series.is_empty());
+        assert_eq!(frames.frames.len(), 2);
+        let f = &frames.frames[0];
+        assert_eq!((f.p1, f.p2), (pos(-10.0, -10.0), pos(100.0, 100.0)));
+        assert_eq!(f.elems.len(), 2);
+        assert_eq!(f.elems[1].msg.as_deref(), Some("box"));
+        assert!(matches!(f.elems[1].shape, Shape::Path(ref p) if p.vp.len() == 3 && p.fill == Some(Color::new(0, 122, 122))));
+        let f = &frames.frames[1];
+        assert!(matches!(f.elems[0].shape, Shape::Circle(ref c) if c.radius == 10.0));
+        assert!(matches!(f.elems[1].shape, Shape::Text(ref t) if t.text == "hi" && t.color == Color::newa(1, 2, 3, 4)));
+        assert!(!f.keyframe && f.step.is_none() && f.events.is_empty() && f.elems[1].attrs.is_empty());
+    }
+
+    #[test]
+    fn hands_out_baseline_frames_before_the_end() {
+        let bytes = include_bytes!("../tests/data/baseline.vis");
+        // the second frame is cut off, the first one arrives all the same
+        let mut first = None;
+        let res = read_packets(&bytes[..bytes.len() - 4], |p| {
+            first.get_or_insert(p);
+        });
+        assert!(res.is_err());
+        assert!(matches!(first, Some(Packet::Frame { track: None, ref frame }) if frame.elems.len() == 2));
+    }
+
+    #[test]
+    fn round_trips_every_field() {
+        let frame = |i: u64| Frame::new(pos(0.0, 0.0), pos(1.0, 1.0))
+            .step(i)
+            .event("restart")
+            .add_element(Circle::new(pos(0.5, 0.5), 0.1).element())
+            .add_element(Circle::new(pos(0.2, 0.2), 0.1).element().with_attr("cost", 2.5).with_layer("agents").with_id(7));
+        let frames = Frames::new()
+            .add_frame(frame(0).keyframe())
+            .add_track(Track::new("worker").add_frame(frame(1)))
+            .add_series(Series::new("score").add_sample(0, 1.0).add_sample(1, 0.5));
+        let decoded = Frames::decode(frames.encode().unwrap()).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", frames));
+    }
+
+    #[test]
+    fn missing_appended_fields_are_defaults() {
+        #[derive(Serialize)]
+        struct Older {
+            keyframe: bool,
+            step: Option<u64>,
+        }
+        let bytes = bincode::serialize(&Older { keyframe: true, step: Some(3) }).unwrap();
+        let ext: FrameExt<'static> = from_appended(&bytes).unwrap();
+        assert!(ext.keyframe);
+        assert_eq!(ext.step, Some(3));
+        assert!(ext.events.is_empty() && ext.attrs.is_empty());
+    }
+
+    #[test]
+    fn unknown_records_are_skipped() {
+        let mut bytes = vec![];
+        write_header(&mut bytes).unwrap();
+        let record = [PACKET_KINDS.to_le_bytes(), [1, 2, 3, 4]].concat();
+        bytes.extend((record.len() as u64).to_le_bytes());
+        bytes.extend(record);
+        write_packet(&mut bytes, &Packet::Sample { series: "x".into(), step: 1, value: 2.0 }).unwrap();
+        let frames = Frames::decode(bytes).unwrap();
+        assert_eq!(frames.series[0].samples, vec![(1, 2.0)]);
+    }
+
+    #[test]
+    fn rejects_a_newer_version() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((VERSION + 1).to_le_bytes());
+        assert!(Frames::decode(bytes).is_err());
+    }
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let mut bytes = vec![];
+        write_packet(&mut bytes, &Packet::Sample { series: "x".into(), step: 1, value: 2.0 }).unwrap();
+        assert!(read_packet(&mut &bytes[..]).unwrap().is_some());
+        assert!(read_packet(&mut &bytes[..0]).unwrap().is_none());
+        for cut in [3, 8, bytes.len() - 1] {
+            let err = read_packet(&mut &bytes[..cut]).unwrap_err().to_string();
+            assert!(err.contains("the stream ends"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn large_lengths_need_the_bytes_to_follow() {
+        let mut bytes = MAX_PACKET.to_le_bytes().to_vec();
+        bytes.extend([0; 16]);
+        assert!(read_packet(&mut &bytes[..]).unwrap_err().to_string().contains("16 bytes into a packet"));
+    }
+}
@@ -0,0 +1,306 @@
+//! Vector arithmetic on `Pos` and generators for common constructions.
+//!
+//! Closed outlines repeat their first vertex at the end, so they are drawn closed
+//! with or without a fill.
+
+use std::ops::{ Add, AddAssign, Div, Mul, Neg, Sub, SubAssign };
+use std::f32::consts::{ PI, TAU };
+use crate::*;
+
+impl Add for Pos {
+    type Output = Pos;
+    fn add(self, rhs: Pos) -> Pos {
+        pos(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for Pos {
+    type Output = Pos;
+    fn sub(self, rhs: Pos) -> Pos {
+        pos(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl AddAssign for Pos {
+    fn add_assign(&mut self, rhs: Pos) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Pos {
+    fn sub_assign(&mut self, rhs: Pos) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<f32> for Pos {
+    type Output = Pos;
+    fn mul(self, rhs: f32) -> Pos {
+        pos(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Mul<Pos> for f32 {
+    type Output = Pos;
+    fn mul(self, rhs: Pos) -> Pos {
+        rhs * self
+    }
+}
+
+impl Div<f32> for Pos {
+    type Output = Pos;
+    fn div(self, rhs: f32) -> Pos {
+        pos(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl Neg for Pos {
+    type Output = Pos;
+    fn neg(self) -> Pos {
+        pos(-self.x, -self.y)
+    }
+}
+
+impl From<(f32, f32)> for Pos {
+    fn from((x, y): (f32, f32)) -> Pos {
+        pos(x, y)
+    }
+}
+
+impl From<[f32; 2]> for Pos {
+    fn from([x, y]: [f32; 2]) -> Pos {
+        pos(x, y)
+    }
+}
+
+impl Pos {
+    pub fn scale(self, s: f32) -> Pos {
+        self * s
+    }
+    pub fn dot(self, rhs: Pos) -> f32 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+    /// The z component of the 3D cross product.
+    pub fn cross(self, rhs: Pos) -> f32 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+    /// Rotates around the origin by `angle` radians.
+    pub fn rotate(self, angle: f32) -> Pos {
+        let (s, c) = angle.sin_cos();
+        pos(self.x * c - self.y * s, self.x * s + self.y * c)
+    }
+    pub fn rotate_around(self, center: Pos, angle: f32) -> Pos {
+        center + (self - center).rotate(angle)
+    }
+    pub fn lerp(self, rhs: Pos, t: f32) -> Pos {
+        self + (rhs - self) * t
+    }
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+    pub fn distance(self, rhs: Pos) -> f32 {
+        (rhs - self).length()
+    }
+    /// The unit vector in the same direction, or zero for the zero vector.
+    pub fn normalized(self) -> Pos {
+        let l = self.length();
+        if l > 0.0 { self / l } else { pos(0.0, 0.0) }
+    }
+    /// The vector rotated by 90 degrees.
+    pub fn perp(self) -> Pos {
+        pos(-self.y, self.x)
+    }
+}
+
+fn closed(mut vp: Vec<Pos>) -> Path {
+    if let Some(&first) = vp.first() {
+        vp.push(first);
+    }
+    Path::from_vertices(vp)
+}
+
+/// A regular polygon with `n` vertices on a circle, the first one at `angle` radians.
+pub fn regular_polygon(center: Pos, radius: f32, n: usize, angle: f32) -> Path {
+    closed((0..n).map(|i| center + pos(radius, 0.0).rotate(angle + TAU * i as f32 / n as f32)).collect())
+}
+
+/// A star with `points` spikes alternating between the outer and inner radius.
+/// The viewer fills only convex paths correctly, so give a star a stroke rather than a fill.
+pub fn star(center: Pos, outer: f32, inner: f32, points: usize) -> Path {
+    closed((0..2 * points).map(|i| {
+        let r = if i % 2 == 0 { outer } else { inner };
+        center + pos(0.0, -r).rotate(PI * i as f32 / points as f32)
+    }).collect())
+}
+
+/// An axis-aligned rectangle between two corners.
+pub fn rect(p1: Pos, p2: Pos) -> Path {
+    closed(vec![p1, pos(p2.x, p1.y), p2, pos(p1.x, p2.y)])
+}
+
+/// A rectangle whose corners are quarter circles of `radius`, each made of `segments` edges.
+pub fn rounded_rect(p1: Pos, p2: Pos, radius: f32, segments: usize) -> Path {
+    let (min, max) = (pos(p1.x.min(p2.x), p1.y.min(p2.y)), pos(p1.x.max(p2.x), p1.y.max(p2.y)));
+    let r = radius.min((max.x - min.x) / 2.0).min((max.y - min.y) / 2.0).max(0.0);
+    let segments = segments.max(1);
+    let corners = [
+        (pos(max.x - r, max.y - r), 0.0),
+        (pos(min.x + r, max.y - r), PI / 2.0),
+        (pos(min.x + r, min.y + r), PI),
+        (pos(max.x - r, min.y + r), PI * 1.5),
+    ];
+    closed(corners.iter().flat_map(|&(c, start)| {
+        (0..=segments).map(move |i| c + pos(r, 0.0).rotate(start + PI / 2.0 * i as f32 / segments as f32))
+    }).collect())
+}
+
+/// A hexagon with circumradius `size`, with a vertex at the top when `pointy_top`.
+pub fn hexagon(center: Pos, size: f32, pointy_top: bool) -> Path {
+    regular_polygon(center, size, 6, if pointy_top { PI / 6.0 } else { 0.0 })
+}
+
+/// The box of cell `(x, y)` in a grid of `cell`-sized squares starting at `origin`.
+pub fn cell_box(origin: Pos, cell: f32, x: usize, y: usize) -> Path {
+    let p1 = origin + pos(x as f32, y as f32) * cell;
+    rect(p1, p1 + pos(cell, cell))
+}
+
+/// The lines of a grid with `cols` x `rows` cells between two corners.
+pub fn grid_lines(p1: Pos, p2: Pos, cols: usize, rows: usize) -> Vec<Path> {
+    let vertical = (0..=cols).map(|i| {
+        let x = p1.x + (p2.x - p1.x) * i as f32 / cols.max(1) as f32;
+        Path::from_vertices(vec![pos(x, p1.y), pos(x, p2.y)])
+    });
+    let horizontal = (0..=rows).map(|j| {
+        let y = p1.y + (p2.y - p1.y) * j as f32 / rows.max(1) as f32;
+        Path::from_vertices(vec![pos(p1.x, y), pos(p2.x, y)])
+    });
+    vertical.chain(horizontal).collect()
+}
+
+/// The polyline moved sideways by `d` along the `perp()` of its segments, with mitered corners.
+pub fn offset_polyline(vp: &[Pos], d: f32) -> Path {
+    let normal = |a: Pos, b: Pos| (b - a).normalized().perp();
+    Path::from_vertices((0..vp.len()).map(|i| {
+        let n1 = if i > 0 { normal(vp[i - 1], vp[i]) } else { normal(vp[i], vp[(i + 1).min(vp.len() - 1)]) };
+        let n2 = if i + 1 < vp.len() { normal(vp[i], vp[i + 1]) } else { n1 };
+        let m = (n1 + n2).normalized();
+        let cos = m.dot(n2);
+        if cos.abs() < 1e-3 { vp[i] + n2 * d } else { vp[i] + m * (d / cos) }
+    }).collect())
+}
+
+/// The circle through three points, or `None` if they are collinear.
+pub fn circumcircle(a: Pos, b: Pos, c: Pos) -> Option<Circle> {
+    let (ab, ac) = (b - a, c - a);
+    let det = 2.0 * ab.cross(ac);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let center = a + pos(ac.y * ab.dot(ab) - ab.y * ac.dot(ac), ab.x * ac.dot(ac) - ac.x * ab.dot(ab)) / det;
+    Some(Circle::new(center, center.distance(a)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Pos, b: Pos) -> bool {
+        a.distance(b) < 1e-4
+    }
+
+    fn all_close(a: &[Pos], b: &[Pos]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| close(a, b))
+    }
+
+    #[test]
+    fn pos_operators() {
+        let (a, b) = (pos(1.0, 2.0), pos(3.0, -1.0));
+        assert_eq!(a + b, pos(4.0, 1.0));
+        assert_eq!(a - b, pos(-2.0, 3.0));
+        assert_eq!(a * 2.0, 2.0 * a);
+        assert_eq!(a / 2.0, pos(0.5, 1.0));
+        assert_eq!(-a, pos(-1.0, -2.0));
+        let mut c = a;
+        c += b;
+        c -= a;
+        assert_eq!(c, b);
+        assert_eq!(Pos::from((1.0, 2.0)), Pos::from([1.0, 2.0]));
+        assert_eq!((a.dot(b), a.cross(b)), (1.0, -7.0));
+        assert!(close(pos(1.0, 0.0).rotate(PI / 2.0), pos(0.0, 1.0)));
+        assert!(close(pos(2.0, 1.0).rotate_around(pos(1.0, 1.0), PI), pos(0.0, 1.0)));
+        assert_eq!(pos(1.0, 0.0).perp(), pos(0.0, 1.0));
+        assert_eq!(pos(3.0, 4.0).length(), 5.0);
+        assert_eq!(pos(0.0, 0.0).normalized(), pos(0.0, 0.0));
+        assert_eq!(a.lerp(b, 0.5), pos(2.0, 0.5));
+    }
+
+    #[test]
+    fn outlines_are_closed() {
+        let polygon = regular_polygon(pos(0.0, 0.0), 2.0, 5, 0.0);
+        assert_eq!(polygon.vp.len(), 6);
+        assert_eq!(polygon.vp[0], polygon.vp[5]);
+        assert!(polygon.vp.iter().all(|v| (v.length() - 2.0).abs() < 1e-4));
+        let hexagon = hexagon(pos(1.0, 1.0), 1.0, true);
+        assert_eq!(hexagon.vp.len(), 7);
+        assert!(hexagon.vp.iter().any(|&v| close(v, pos(1.0, 2.0))));
+        assert_eq!(rect(pos(0.0, 0.0), pos(2.0, 1.0)).vp, vec![pos(0.0, 0.0), pos(2.0, 0.0), pos(2.0, 1.0), pos(0.0, 1.0), pos(0.0, 0.0)]);
+        assert_eq!(cell_box(pos(1.0, 1.0), 2.0, 1, 2).vp[0], pos(3.0, 5.0));
+    }
+
+    #[test]
+    fn stars_alternate_between_the_radii() {
+        let star = star(pos(0.0, 0.0), 2.0, 1.0, 5);
+        assert_eq!(star.vp.len(), 11);
+        for (i, v) in star.vp[..10].iter().enumerate() {
+            assert!((v.length() - if i % 2 == 0 { 2.0 } else { 1.0 }).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn rounded_rects_have_segments_per_corner() {
+        let r = rounded_rect(pos(0.0, 0.0), pos(10.0, 4.0), 1.0, 3);
+        assert_eq!(r.vp.len(), 4 * 4 + 1);
+        assert!(r.vp.iter().all(|v| (0.0..=10.0).contains(&v.x) && (-1e-4..=4.0 + 1e-4).contains(&v.y)));
+        // the radius is capped at half the shorter side, and zero segments make one
+        assert_eq!(rounded_rect(pos(0.0, 0.0), pos(10.0, 4.0), 5.0, 0).vp.len(), 4 * 2 + 1);
+        assert!(rounded_rect(pos(10.0, 4.0), pos(0.0, 0.0), 5.0, 2).vp.iter().all(|v| v.y >= -1e-4 && v.y <= 4.0 + 1e-4));
+    }
+
+    #[test]
+    fn grid_lines_cover_every_cell() {
+        let lines = grid_lines(pos(0.0, 0.0), pos(3.0, 2.0), 3, 2);
+        assert_eq!(lines.len(), 4 + 3);
+        assert_eq!(lines[1].vp, vec![pos(1.0, 0.0), pos(1.0, 2.0)]);
+        assert_eq!(lines[5].vp, vec![pos(0.0, 1.0), pos(3.0, 1.0)]);
+    }
+
+    #[test]
+    fn offset_polylines_miter_right_angles() {
+        let p = offset_polyline(&[pos(0.0, 0.0), pos(10.0, 0.0), pos(10.0, 10.0)], 1.0);
+        assert!(all_close(&p.vp, &[pos(0.0, 1.0), pos(9.0, 1.0), pos(9.0, 10.0)]), "{:?}", p.vp);
+    }
+
+    #[test]
+    fn offset_polylines_shift_straight_segments() {
+        let p = offset_polyline(&[pos(0.0, 0.0), pos(5.0, 0.0), pos(10.0, 0.0)], -2.0);
+        assert!(all_close(&p.vp, &[pos(0.0, -2.0), pos(5.0, -2.0), pos(10.0, -2.0)]), "{:?}", p.vp);
+        let p = offset_polyline(&[pos(0.0, 0.0), pos(0.0, 3.0)], 1.0);
+        assert!(all_close(&p.vp, &[pos(-1.0, 0.0), pos(-1.0, 3.0)]), "{:?}", p.vp);
+    }
+
+    #[test]
+    fn circumcircles_pass_through_the_points() {
+        let c = circumcircle(pos(1.0, 0.0), pos(0.0, 1.0), pos(-1.0, 0.0)).unwrap();
+        assert!(close(c.center, pos(0.0, 0.0)) && (c.radius - 1.0).abs() < 1e-4);
+        let c = circumcircle(pos(2.0, 3.0), pos(6.0, 3.0), pos(2.0, 6.0)).unwrap();
+        assert!(close(c.center, pos(4.0, 4.5)) && (c.radius - 2.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn collinear_points_have_no_circumcircle() {
+        assert!(circumcircle(pos(0.0, 0.0), pos(1.0, 1.0), pos(2.0, 2.0)).is_none());
+        assert!(circumcircle(pos(0.0, 0.0), pos(0.0, 0.0), pos(1.0, 0.0)).is_none());
+        assert!(circumcircle(pos(1.0, 1.0), pos(1.0, 1.0), pos(1.0, 1.0)).is_none());
+    }
+}
@@ -0,0 +1,522 @@
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub label: Option<String>,
+    pub color: Color,
+    pub radius: f32,
+    pub msg: Option<String>,
+    /// A manual position. Layouts keep such nodes pinned.
+    pub pos: Option<Pos>,
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self {
+            label: None,
+            color: Color::new(255, 255, 255),
+            radius: 5.0,
+            msg: None,
+            pos: None,
+        }
+    }
+}
+
+impl Node {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+    pub fn with_msg<S: Into<String>>(mut self, msg: S) -> Self {
+        self.msg = Some(msg.into());
+        self
+    }
+    pub fn pos(mut self, pos: Pos) -> Self {
+        self.pos = Some(pos);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub directed: bool,
+    pub weight: Option<f32>,
+    pub stroke: Stroke,
+    pub msg: Option<String>,
+}
+
+impl Edge {
+    pub fn new(from: usize, to: usize) -> Self {
+        Self { from, to, directed: false, weight: None, stroke: Stroke::default(), msg: None }
+    }
+    pub fn directed(mut self) -> Self {
+        self.directed = true;
+        self
+    }
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+    pub fn stroke(mut self, color: Color, width: f32) -> Self {
+        self.stroke = Stroke { color, width };
+        self
+    }
+    pub fn with_msg<S: Into<String>>(mut self, msg: S) -> Self {
+        self.msg = Some(msg.into());
+        self
+    }
+}
+
+/// A graph drawn as circles, lines and labels. Nodes are identified by the order they are added.
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// A graph with `n` nodes labeled by their index.
+    pub fn with_nodes(n: usize) -> Self {
+        Self {
+            nodes: (0..n).map(|i| Node::new().label(i.to_string())).collect(),
+            edges: vec![],
+        }
+    }
+    pub fn add_node(mut self, node: Node) -> Self {
+        self.nodes.push(node);
+        self
+    }
+    /// Panics if the edge refers to a node that was not added yet.
+    pub fn add_edge(mut self, edge: Edge) -> Self {
+        let n = self.nodes.len();
+        assert!(edge.from < n && edge.to < n, "edge {} -> {} refers to a missing node, the graph has {} nodes", edge.from, edge.to, n);
+        self.edges.push(edge);
+        self
+    }
+
+    /// The nodes sharing an edge with `v`.
+    fn neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges.iter().filter_map(move |e| if e.from == v { Some(e.to) } else if e.to == v { Some(e.from) } else { None })
+    }
+
+    /// Edges first, then nodes with their labels, at the given node positions.
+    /// Edges to missing nodes are skipped, and an edge from a node to itself is a small loop.
+    pub fn elements(&self, positions: &[Pos]) -> Vec<Element> {
+        let mut res = vec![];
+        for e in self.edges.iter() {
+            let (Some(&a), Some(&b)) = (positions.get(e.from), positions.get(e.to)) else { continue };
+            let (Some(na), Some(nb)) = (self.nodes.get(e.from), self.nodes.get(e.to)) else { continue };
+            let (ra, rb) = (na.radius, nb.radius);
+            if e.from == e.to {
+                res.extend(self_loop(e, a, ra));
+                continue;
+            }
+            let (dx, dy) = (b.x - a.x, b.y - a.y);
+            let len = (dx * dx + dy * dy).sqrt();
+            let (ux, uy) = if len > 0.0 { (dx / len, dy / len) } else { (1.0, 0.0) };
+            // overlapping nodes are joined center to center, under the circles
+            let overlap = len <= ra + rb;
+            let (start, end) = if overlap { (a, b) } else { (pos(a.x + ux * ra, a.y + uy * ra), pos(b.x - ux * rb, b.y - uy * rb)) };
+            let mut path = Path::from_vertices(vec![start, end]).stroke(e.stroke.color, e.stroke.width).element();
+            path.msg = e.msg.clone();
+            res.push(path);
+            if e.directed && !overlap {
+                let (hl, hw) = (rb * 0.8 + e.stroke.width, rb * 0.4 + e.stroke.width);
+                let base = pos(end.x - ux * hl, end.y - uy * hl);
+                res.push(
+                    Path::from_vertices(vec![end, pos(base.x - uy * hw, base.y + ux * hw), pos(base.x + uy * hw, base.y - ux * hw)])
+                        .close(e.stroke.color)
+                        .stroke(e.stroke.color, e.stroke.width)
+                        .element()
+                );
+            }
+            if let Some(w) = e.weight {
+                let size = (ra + rb) * 0.5;
+                res.push(
+                    Text::new(w.to_string(), size, pos((start.x + end.x) / 2.0 - uy * size * 0.6, (start.y + end.y) / 2.0 + ux * size * 0.6))
+                        .color(e.stroke.color)
+                        .element()
+                );
+            }
+        }
+        for (node, &p) in self.nodes.iter().zip(positions.iter()) {
+            let mut circle = Circle::new(p, node.radius).fill(node.color).stroke(Color::new(0, 0, 0), 1.0).element();
+            circle.msg = node.msg.clone();
+            res.push(circle);
+            if let Some(ref label) = node.label {
+                res.push(Text::new(label.clone(), node.radius, p).element());
+            }
+        }
+        res
+    }
+}
+
+/// An edge from a node to itself: a small loop on the upper right of the node at `p`.
+fn self_loop(e: &Edge, p: Pos, r: f32) -> Vec<Element> {
+    const SEGMENTS: usize = 16;
+    let lr = r * 0.6;
+    let c = pos(p.x + r * std::f32::consts::FRAC_1_SQRT_2, p.y - r * std::f32::consts::FRAC_1_SQRT_2);
+    let vp = (0..=SEGMENTS).map(|i| {
+        let t = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        pos(c.x + lr * t.cos(), c.y + lr * t.sin())
+    }).collect();
+    let mut path = Path::from_vertices(vp).stroke(e.stroke.color, e.stroke.width).element();
+    path.msg = e.msg.clone();
+    let mut res = vec![path];
+    if let Some(w) = e.weight {
+        res.push(Text::new(w.to_string(), r, pos(c.x + lr * 1.5, c.y - lr * 1.5)).color(e.stroke.color).element());
+    }
+    res
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Fruchterman-Reingold springs, warm-started from the previous positions.
+    ForceDirected,
+    /// BFS layers from the nodes without incoming edges, for trees and DAGs. Nodes keep their
+    /// order within a layer from the previous positions.
+    Layered,
+    /// Nodes keep their order around the circle from the previous positions, and a new node goes
+    /// next to a neighbor.
+    Circular,
+}
+
+/// Computes node positions on the producer side. Keep one `GraphLayout` across frames so
+/// that nodes stay where they were.
+#[derive(Debug, Clone)]
+pub struct GraphLayout {
+    pub layout: Layout,
+    pub spacing: f32,
+    pub iterations: usize,
+    positions: Vec<Pos>,
+    edges: Vec<(usize, usize, bool)>,
+    pins: Vec<Option<Pos>>,
+}
+
+fn dist(a: Pos, b: Pos) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+impl GraphLayout {
+    pub fn new(layout: Layout) -> Self {
+        Self { layout, spacing: 30.0, iterations: 300, positions: vec![], edges: vec![], pins: vec![] }
+    }
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// The node positions for `graph`, reusing the positions of the previous call.
+    /// A graph with the same nodes, edges and pins as last time keeps its positions as they are.
+    pub fn positions(&mut self, graph: &Graph) -> &[Pos] {
+        let n = graph.nodes.len();
+        let edges = graph.edges.iter().map(|e| (e.from, e.to, e.directed)).collect::<Vec<_>>();
+        let pins = graph.nodes.iter().map(|node| node.pos).collect::<Vec<_>>();
+        if self.positions.len() == n && edges == self.edges && pins == self.pins {
+            return &self.positions;
+        }
+        self.edges = edges;
+        self.pins = pins;
+        let mut pos = match self.layout {
+            Layout::ForceDirected => self.force_directed(graph),
+            Layout::Layered => self.layered(graph),
+            Layout::Circular => self.circular(graph),
+        };
+        for (p, node) in pos.iter_mut().zip(graph.nodes.iter()) {
+            if let Some(pinned) = node.pos {
+                *p = pinned;
+            }
+        }
+        self.positions = pos;
+        &self.positions
+    }
+
+    fn circular(&self, graph: &Graph) -> Vec<Pos> {
+        let n = graph.nodes.len();
+        let prev = &self.positions[..self.positions.len().min(n)];
+        // the angles start at node 0, as in the layout without previous positions
+        let angle = |p: Pos| p.y.atan2(p.x).rem_euclid(std::f32::consts::TAU);
+        let mut order = (0..prev.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| angle(prev[a]).total_cmp(&angle(prev[b])));
+        for v in prev.len()..n {
+            match graph.neighbors(v).filter(|&u| u < v).find_map(|u| order.iter().position(|&w| w == u)) {
+                Some(i) => order.insert(i + 1, v),
+                None => order.push(v),
+            }
+        }
+        let r = (n as f32 * self.spacing / std::f32::consts::TAU).max(self.spacing);
+        let mut res = vec![pos(0.0, 0.0); n];
+        for (i, &v) in order.iter().enumerate() {
+            let t = std::f32::consts::TAU * i as f32 / n as f32;
+            res[v] = pos(r * t.cos(), r * t.sin());
+        }
+        res
+    }
+
+    fn layered(&self, graph: &Graph) -> Vec<Pos> {
+        let n = graph.nodes.len();
+        let mut adj = vec![vec![]; n];
+        let mut indeg = vec![0; n];
+        for e in graph.edges.iter().filter(|e| e.from < n && e.to < n) {
+            adj[e.from].push(e.to);
+            indeg[e.to] += 1;
+            if !e.directed {
+                adj[e.to].push(e.from);
+            }
+        }
+        let mut layer = vec![usize::MAX; n];
+        let mut layers: Vec<Vec<usize>> = vec![];
+        let roots = (0..n).filter(|&v| indeg[v] == 0).chain(0..n).collect::<Vec<_>>();
+        for root in roots {
+            if layer[root] != usize::MAX {
+                continue;
+            }
+            layer[root] = 0;
+            let mut queue = std::collections::VecDeque::from([root]);
+            while let Some(v) = queue.pop_front() {
+                if layers.len() <= layer[v] {
+                    layers.push(vec![]);
+                }
+                layers[layer[v]].push(v);
+                for &u in adj[v].iter() {
+                    if layer[u] == usize::MAX {
+                        layer[u] = layer[v] + 1;
+                        queue.push_back(u);
+                    }
+                }
+            }
+        }
+        // new nodes go below the average of their placed neighbors, or at the end of their layer
+        let prev = &self.positions;
+        let x = |v: usize| prev.get(v).map(|p| p.x).unwrap_or_else(|| {
+            let xs = graph.neighbors(v).filter_map(|u| prev.get(u).map(|p| p.x)).collect::<Vec<_>>();
+            if xs.is_empty() { f32::INFINITY } else { xs.iter().sum::<f32>() / xs.len() as f32 }
+        });
+        for vs in layers.iter_mut() {
+            vs.sort_by(|&a, &b| x(a).total_cmp(&x(b)));
+        }
+        let mut res = vec![pos(0.0, 0.0); n];
+        for (d, vs) in layers.iter().enumerate() {
+            let width = (vs.len() - 1) as f32 * self.spacing;
+            for (i, &v) in vs.iter().enumerate() {
+                res[v] = pos(i as f32 * self.spacing - width / 2.0, d as f32 * self.spacing);
+            }
+        }
+        res
+    }
+
+    fn force_directed(&self, graph: &Graph) -> Vec<Pos> {
+        let n = graph.nodes.len();
+        let k = self.spacing;
+        let warm = !self.positions.is_empty();
+        let mut p = self.positions.clone();
+        p.truncate(n);
+        let fresh = self.circular(graph);
+        while p.len() < n {
+            // a new node starts next to its placed neighbors, or on a circle
+            let v = p.len();
+            let placed = graph.neighbors(v).filter(|&u| u < v).collect::<Vec<_>>();
+            if placed.is_empty() {
+                p.push(fresh[v]);
+            }
+            else {
+                let c = placed.iter().fold(pos(0.0, 0.0), |c, &u| pos(c.x + p[u].x, c.y + p[u].y));
+                let t = v as f32;
+                p.push(pos(c.x / placed.len() as f32 + k * 0.3 * t.cos(), c.y / placed.len() as f32 + k * 0.3 * t.sin()));
+            }
+        }
+        let pinned = graph.nodes.iter().map(|node| node.pos).collect::<Vec<_>>();
+        for (q, pin) in p.iter_mut().zip(pinned.iter()) {
+            if let Some(pin) = pin {
+                *q = *pin;
+            }
+        }
+        // a warm start only relaxes the previous layout instead of shaking it up
+        let t0 = if warm { k * 0.05 } else { k * 2.0 };
+        let iterations = if warm { self.iterations / 4 } else { self.iterations };
+        for it in 0..iterations {
+            let mut disp = vec![(0.0f32, 0.0f32); n];
+            for i in 0..n {
+                for j in i + 1..n {
+                    let (mut dx, mut dy) = (p[i].x - p[j].x, p[i].y - p[j].y);
+                    if dx.abs() + dy.abs() < 1e-3 {
+                        dx = ((i * 7 + j * 13) % 5) as f32 * 0.1 + 0.1;
+                        dy = ((i * 11 + j * 3) % 5) as f32 * 0.1 - 0.2;
+                    }
+                    let d = (dx * dx + dy * dy).sqrt();
+                    let f = k * k / d / d;
+                    disp[i].0 += dx * f;
+                    disp[i].1 += dy * f;
+                    disp[j].0 -= dx * f;
+                    disp[j].1 -= dy * f;
+                }
+            }
+            for e in graph.edges.iter().filter(|e| e.from < n && e.to < n && e.from != e.to) {
+                let (dx, dy) = (p[e.from].x - p[e.to].x, p[e.from].y - p[e.to].y);
+                let d = dist(p[e.from], p[e.to]);
+                let f = d / k;
+                disp[e.from].0 -= dx * f;
+                disp[e.from].1 -= dy * f;
+                disp[e.to].0 += dx * f;
+                disp[e.to].1 += dy * f;
+            }
+            let t = t0 * 0.98f32.powi(it as i32);
+            for i in 0..n {
+                if pinned[i].is_some() {
+                    continue;
+                }
+                // weak gravity keeps disconnected components together
+                let (dx, dy) = (disp[i].0 - p[i].x * 0.05, disp[i].1 - p[i].y * 0.05);
+                let d = (dx * dx + dy * dy).sqrt();
+                if d > 0.0 && d.is_finite() {
+                    let step = (d * 0.1).min(t);
+                    p[i] = pos(p[i].x + dx / d * step, p[i].y + dy / d * step);
+                }
+            }
+        }
+        p
+    }
+}
+
+impl Frame {
+    /// Adds the elements of `graph`, placed by `layout`.
+    pub fn add_graph(mut self, graph: &Graph, layout: &mut GraphLayout) -> Self {
+        for elem in graph.elements(layout.positions(graph)) {
+            self.push_element(elem);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_graph(n: usize) -> Graph {
+        (1..n).fold(Graph::with_nodes(n), |g, i| g.add_edge(Edge::new(i - 1, i)))
+    }
+
+    #[test]
+    #[should_panic(expected = "missing node")]
+    fn edges_to_missing_nodes_are_rejected() {
+        let _ = Graph::with_nodes(2).add_edge(Edge::new(0, 2));
+    }
+
+    #[test]
+    fn elements_skip_edges_to_missing_nodes() {
+        let mut graph = Graph::with_nodes(2);
+        graph.edges.push(Edge::new(0, 5));
+        let elems = graph.elements(&[pos(0.0, 0.0), pos(100.0, 0.0)]);
+        assert_eq!(elems.len(), 4);
+    }
+
+    #[test]
+    fn circular_puts_a_new_node_next_to_its_neighbor() {
+        let mut layout = GraphLayout::new(Layout::Circular);
+        layout.positions(&Graph::with_nodes(4));
+        let graph = Graph::with_nodes(5).add_edge(Edge::new(1, 4));
+        let p = layout.positions(&graph).to_vec();
+        let r = p[0].x;
+        let slot = |q: Pos| (q.y.atan2(q.x).rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU * 5.0).round() as usize % 5;
+        assert_eq!((0..5).map(|v| slot(p[v])).collect::<Vec<_>>(), vec![0, 1, 3, 4, 2]);
+        assert!((dist(p[0], pos(0.0, 0.0)) - r).abs() < 1e-3);
+    }
+
+    #[test]
+    fn layered_keeps_the_order_within_a_layer() {
+        let mut layout = GraphLayout::new(Layout::Layered);
+        let graph = Graph::with_nodes(3).add_edge(Edge::new(0, 2).directed()).add_edge(Edge::new(0, 1).directed());
+        let before = layout.positions(&graph).to_vec();
+        assert!(before[2].x < before[1].x);
+        let graph = graph.add_edge(Edge::new(1, 2));
+        let after = layout.positions(&graph).to_vec();
+        assert!(after[2].x < after[1].x);
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn positions_are_kept_for_an_unchanged_graph() {
+        let mut layout = GraphLayout::new(Layout::ForceDirected).iterations(20);
+        let before = layout.positions(&path_graph(5)).to_vec();
+        assert_eq!(layout.positions(&path_graph(5)), &before[..]);
+    }
+
+    fn min_distance(p: &[Pos]) -> f32 {
+        let mut res = f32::INFINITY;
+        for i in 0..p.len() {
+            for j in i + 1..p.len() {
+                res = res.min(dist(p[i], p[j]));
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn force_directed_separates_the_nodes() {
+        let mut complete = Graph::with_nodes(6);
+        for i in 0..6 {
+            for j in i + 1..6 {
+                complete = complete.add_edge(Edge::new(i, j));
+            }
+        }
+        for graph in [Graph::with_nodes(6), path_graph(6), complete] {
+            let mut layout = GraphLayout::new(Layout::ForceDirected);
+            assert!(min_distance(layout.positions(&graph)) > layout.spacing * 0.5);
+        }
+        // even from a start where they all coincide
+        let mut layout = GraphLayout::new(Layout::ForceDirected);
+        layout.positions = vec![pos(0.0, 0.0); 4];
+        assert!(min_distance(layout.positions(&path_graph(4))) > layout.spacing * 0.2);
+    }
+
+    #[test]
+    fn force_directed_is_deterministic() {
+        let graph = path_graph(8).add_edge(Edge::new(0, 7)).add_edge(Edge::new(2, 5));
+        let a = GraphLayout::new(Layout::ForceDirected).positions(&graph).to_vec();
+        let b = GraphLayout::new(Layout::ForceDirected).positions(&graph).to_vec();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn overlapping_nodes_are_joined_center_to_center() {
+        let graph = Graph::with_nodes(2).add_edge(Edge::new(0, 1).directed());
+        let elems = graph.elements(&[pos(0.0, 0.0), pos(6.0, 0.0)]);
+        let Shape::Path(ref edge) = elems[0].shape else { panic!("the edge is not a path") };
+        assert_eq!(edge.vp, vec![pos(0.0, 0.0), pos(6.0, 0.0)]);
+        // no arrow head, which would point into the other circle
+        assert!(matches!(elems[1].shape, Shape::Circle(_)));
+    }
+
+    #[test]
+    fn self_loops_are_drawn_as_loops() {
+        let graph = Graph::with_nodes(1).add_edge(Edge::new(0, 0).weight(2.0).with_msg("loop"));
+        let elems = graph.elements(&[pos(10.0, 10.0)]);
+        let Shape::Path(ref edge) = elems[0].shape else { panic!("the edge is not a path") };
+        assert_eq!(elems[0].msg.as_deref(), Some("loop"));
+        assert!(edge.vp.len() > 3 && edge.fill.is_none());
+        assert!(dist(edge.vp[0], *edge.vp.last().unwrap()) < 1e-3);
+        // it sticks out of the node
+        assert!(edge.vp.iter().any(|&v| dist(v, pos(10.0, 10.0)) > 5.0));
+        assert!(matches!(elems[1].shape, Shape::Text(_)));
+    }
+}
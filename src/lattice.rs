@@ -0,0 +1,321 @@
+//! Coordinate systems for hex, triangular and isometric boards, and `Grid`, a compact
+//! shape of lattice cells whose coordinates the viewer shows on hover.
+
+use serde::{ Serialize, Deserialize };
+use crate::*;
+
+const SQRT3: f32 = 1.732_050_8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LatticeKind {
+    /// Axial `(q, r)` hex coordinates.
+    HexAxial { pointy_top: bool },
+    /// Offset `(col, row)` hex coordinates. Pointy-top lattices shove every odd (or even)
+    /// row right, flat-top lattices shove every odd (or even) column down.
+    HexOffset { pointy_top: bool, odd: bool },
+    /// `(col, row)` triangles, pointing up when `col + row` is even.
+    Triangular,
+    /// `(i, j)` diamonds, `i` going right-down and `j` going left-down.
+    Isometric,
+}
+
+/// A lattice placed in world coordinates. `size` is the hex circumradius, the triangle
+/// side or the diamond width.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Lattice {
+    pub kind: LatticeKind,
+    pub origin: Pos,
+    pub size: f32,
+}
+
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    }
+    else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i32, rr as i32)
+}
+
+impl Lattice {
+    pub fn new(kind: LatticeKind, origin: Pos, size: f32) -> Self {
+        Self { kind, origin, size }
+    }
+    pub fn hex_axial(origin: Pos, size: f32, pointy_top: bool) -> Self {
+        Self::new(LatticeKind::HexAxial { pointy_top }, origin, size)
+    }
+    pub fn hex_offset(origin: Pos, size: f32, pointy_top: bool, odd: bool) -> Self {
+        Self::new(LatticeKind::HexOffset { pointy_top, odd }, origin, size)
+    }
+    pub fn triangular(origin: Pos, size: f32) -> Self {
+        Self::new(LatticeKind::Triangular, origin, size)
+    }
+    pub fn isometric(origin: Pos, size: f32) -> Self {
+        Self::new(LatticeKind::Isometric, origin, size)
+    }
+
+    fn offset_to_axial(pointy_top: bool, odd: bool, (col, row): (i32, i32)) -> (i32, i32) {
+        let shift = |k: i32| if odd { (k - (k & 1)) / 2 } else { (k + (k & 1)) / 2 };
+        if pointy_top { (col - shift(row), row) } else { (col, row - shift(col)) }
+    }
+
+    fn axial_to_offset(pointy_top: bool, odd: bool, (q, r): (i32, i32)) -> (i32, i32) {
+        let shift = |k: i32| if odd { (k - (k & 1)) / 2 } else { (k + (k & 1)) / 2 };
+        if pointy_top { (q + shift(r), r) } else { (q, r + shift(q)) }
+    }
+
+    fn hex_center(&self, pointy_top: bool, (q, r): (i32, i32)) -> Pos {
+        let (q, r) = (q as f32, r as f32);
+        let p = if pointy_top {
+            pos(SQRT3 * (q + r / 2.0), 1.5 * r)
+        }
+        else {
+            pos(1.5 * q, SQRT3 * (r + q / 2.0))
+        };
+        self.origin + p * self.size
+    }
+
+    /// The center of cell `(i, j)`.
+    pub fn center(&self, i: i32, j: i32) -> Pos {
+        match self.kind {
+            LatticeKind::HexAxial { pointy_top } => self.hex_center(pointy_top, (i, j)),
+            LatticeKind::HexOffset { pointy_top, odd } => self.hex_center(pointy_top, Self::offset_to_axial(pointy_top, odd, (i, j))),
+            LatticeKind::Triangular => {
+                let h = self.size * SQRT3 / 2.0;
+                let up = (i + j).rem_euclid(2) == 0;
+                self.origin + pos((i as f32 + 1.0) * self.size / 2.0, j as f32 * h + if up { h * 2.0 / 3.0 } else { h / 3.0 })
+            }
+            LatticeKind::Isometric => {
+                self.origin + pos((i - j) as f32 * self.size / 2.0, (i + j) as f32 * self.size / 4.0)
+            }
+        }
+    }
+
+    /// The corners of cell `(i, j)`, without repeating the first one.
+    pub fn vertices(&self, i: i32, j: i32) -> Vec<Pos> {
+        let c = self.center(i, j);
+        match self.kind {
+            LatticeKind::HexAxial { pointy_top } | LatticeKind::HexOffset { pointy_top, .. } => {
+                let mut vp = geometry::hexagon(c, self.size, pointy_top).vp;
+                vp.pop();
+                vp
+            }
+            LatticeKind::Triangular => {
+                let (s, h) = (self.size, self.size * SQRT3 / 2.0);
+                let x = self.origin.x + i as f32 * s / 2.0;
+                let y = self.origin.y + j as f32 * h;
+                if (i + j).rem_euclid(2) == 0 {
+                    vec![pos(x + s / 2.0, y), pos(x + s, y + h), pos(x, y + h)]
+                }
+                else {
+                    vec![pos(x, y), pos(x + s, y), pos(x + s / 2.0, y + h)]
+                }
+            }
+            LatticeKind::Isometric => {
+                let (w, h) = (self.size / 2.0, self.size / 4.0);
+                vec![c + pos(0.0, -h), c + pos(w, 0.0), c + pos(0.0, h), c + pos(-w, 0.0)]
+            }
+        }
+    }
+
+    /// The closed outline of cell `(i, j)`.
+    pub fn cell(&self, i: i32, j: i32) -> Path {
+        let mut vp = self.vertices(i, j);
+        vp.push(vp[0]);
+        Path::from_vertices(vp)
+    }
+
+    /// The cell containing `p`.
+    pub fn locate(&self, p: Pos) -> (i32, i32) {
+        let d = (p - self.origin) / self.size;
+        match self.kind {
+            LatticeKind::HexAxial { pointy_top } => Self::hex_locate(pointy_top, d),
+            LatticeKind::HexOffset { pointy_top, odd } => Self::axial_to_offset(pointy_top, odd, Self::hex_locate(pointy_top, d)),
+            LatticeKind::Triangular => {
+                let h = SQRT3 / 2.0;
+                let j = (d.y / h).floor() as i32;
+                let fy = d.y / h - j as f32;
+                let i = (d.x * 2.0).floor() as i32;
+                let fx = d.x * 2.0 - i as f32;
+                // in the strip [i, i + 1) the diagonal separates cell i - 1 from cell i
+                let up = (i + j).rem_euclid(2) == 0;
+                let left = if up { fy < 1.0 - fx } else { fy > fx };
+                if left { (i - 1, j) } else { (i, j) }
+            }
+            LatticeKind::Isometric => {
+                let (a, b) = (d.x * 2.0, d.y * 4.0);
+                (((a + b) / 2.0).round() as i32, ((b - a) / 2.0).round() as i32)
+            }
+        }
+    }
+
+    fn hex_locate(pointy_top: bool, d: Pos) -> (i32, i32) {
+        if pointy_top {
+            let r = d.y * 2.0 / 3.0;
+            hex_round(d.x / SQRT3 - r / 2.0, r)
+        }
+        else {
+            let q = d.x * 2.0 / 3.0;
+            hex_round(q, d.y / SQRT3 - q / 2.0)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GridCell {
+    pub index: (i32, i32),
+    pub fill: Option<Color>,
+    pub msg: Option<String>,
+    pub attrs: Vec<(String, f32)>,
+}
+
+impl GridCell {
+    pub fn new(i: i32, j: i32) -> Self {
+        Self { index: (i, j), fill: None, msg: None, attrs: vec![] }
+    }
+    pub fn fill(mut self, fill: Color) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+    pub fn with_msg<S: Into<String>>(mut self, msg: S) -> Self {
+        self.msg = Some(msg.into());
+        self
+    }
+    pub fn with_attr<S: Into<String>>(mut self, name: S, value: f32) -> Self {
+        set_attr(&mut self.attrs, name.into(), value);
+        self
+    }
+}
+
+/// Cells of a lattice drawn as one shape. The viewer reports the lattice coordinates of
+/// the hovered cell.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Grid {
+    pub lattice: Lattice,
+    pub cells: Vec<GridCell>,
+    pub stroke: Stroke,
+}
+
+impl Grid {
+    pub fn new(lattice: Lattice) -> Self {
+        Self { lattice, cells: vec![], stroke: Stroke::default() }
+    }
+    /// The cells `0..cols` x `0..rows`, row by row.
+    pub fn rect(lattice: Lattice, cols: i32, rows: i32) -> Self {
+        Self {
+            cells: (0..rows).flat_map(|j| (0..cols).map(move |i| GridCell::new(i, j))).collect(),
+            ..Self::new(lattice)
+        }
+    }
+    /// The axial cells within `radius` steps of `(0, 0)`, a hexagon-shaped board.
+    pub fn hexagon(lattice: Lattice, radius: i32) -> Self {
+        Self {
+            cells: (-radius..=radius)
+                .flat_map(|r| (-radius..=radius).filter(move |q| (q + r).abs() <= radius).map(move |q| GridCell::new(q, r)))
+                .collect(),
+            ..Self::new(lattice)
+        }
+    }
+    pub fn add_cell(mut self, cell: GridCell) -> Self {
+        self.cells.push(cell);
+        self
+    }
+    pub fn stroke(mut self, color: Color, width: f32) -> Self {
+        self.stroke = Stroke { color, width };
+        self
+    }
+    pub fn element(self) -> Element {
+        Element {
+            shape: Shape::Grid(self),
+            msg: None,
+            attrs: vec![],
+            layer: None,
+            id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lattices() -> Vec<Lattice> {
+        let o = pos(3.0, -2.0);
+        let mut res = vec![Lattice::triangular(o, 10.0), Lattice::isometric(o, 10.0)];
+        for pointy_top in [true, false] {
+            res.push(Lattice::hex_axial(o, 10.0, pointy_top));
+            for odd in [true, false] {
+                res.push(Lattice::hex_offset(o, 10.0, pointy_top, odd));
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn locate_finds_the_cell_of_its_center() {
+        for lattice in lattices() {
+            for i in -4..=4 {
+                for j in -4..=4 {
+                    assert_eq!(lattice.locate(lattice.center(i, j)), (i, j), "{:?}", lattice.kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn locate_finds_the_cell_near_its_corners() {
+        for lattice in lattices() {
+            for (i, j) in [(0, 0), (1, 0), (-3, 2), (2, -1)] {
+                let c = lattice.center(i, j);
+                for v in lattice.vertices(i, j) {
+                    // a point just inside the corner
+                    let p = c + (v - c) * 0.9;
+                    assert_eq!(lattice.locate(p), (i, j), "{:?}", lattice.kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn offset_and_axial_coordinates_round_trip() {
+        for pointy_top in [true, false] {
+            for odd in [true, false] {
+                for c in (-3..=3).flat_map(|i| (-3..=3).map(move |j| (i, j))) {
+                    let axial = Lattice::offset_to_axial(pointy_top, odd, c);
+                    assert_eq!(Lattice::axial_to_offset(pointy_top, odd, axial), c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn triangles_alternate_up_and_down() {
+        let lattice = Lattice::triangular(pos(0.0, 0.0), 2.0);
+        let up = lattice.vertices(0, 0);
+        let down = lattice.vertices(1, 0);
+        assert_eq!(up.len(), 3);
+        assert_eq!(up[0], pos(1.0, 0.0));
+        assert_eq!(down[2], pos(2.0, SQRT3));
+    }
+
+    #[test]
+    fn cells_are_closed() {
+        for lattice in lattices() {
+            let path = lattice.cell(1, 2);
+            assert_eq!(path.vp.first(), path.vp.last());
+        }
+    }
+
+    #[test]
+    fn boards_have_the_expected_cells() {
+        let lattice = Lattice::hex_axial(pos(0.0, 0.0), 1.0, true);
+        assert_eq!(Grid::hexagon(lattice, 0).cells.len(), 1);
+        assert_eq!(Grid::hexagon(lattice, 2).cells.len(), 19);
+        let rect = Grid::rect(lattice, 3, 2);
+        assert_eq!(rect.cells.iter().map(|c| c.index).collect::<Vec<_>>(), vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{ Serialize, Deserialize };
+use crate::*;
+
+/// The corner of the canvas a legend is anchored to.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum LegendKind {
+    /// A colorbar with labels at both ends.
+    Colormap { colormap: Colormap, min: String, max: String },
+    /// Color swatches with labels, one per row.
+    Categorical(Vec<(Color, String)>),
+}
+
+/// Explains the colors of a frame. The viewer draws it in a corner of the canvas,
+/// unaffected by pan and zoom.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Legend {
+    pub title: Option<String>,
+    pub kind: LegendKind,
+    pub corner: Corner,
+}
+
+impl Legend {
+    pub fn colormap<S: Into<String>, T: Into<String>>(colormap: Colormap, min: S, max: T) -> Self {
+        Self {
+            title: None,
+            kind: LegendKind::Colormap { colormap, min: min.into(), max: max.into() },
+            corner: Corner::default(),
+        }
+    }
+    pub fn categorical() -> Self {
+        Self { title: None, kind: LegendKind::Categorical(vec![]), corner: Corner::default() }
+    }
+    /// A categorical legend of `Color::tag(0)`, `Color::tag(1)`, ... labeled in order.
+    pub fn tags<S: Into<String>>(labels: impl IntoIterator<Item = S>) -> Self {
+        labels.into_iter().enumerate().fold(Self::categorical(), |l, (i, label)| l.entry(Color::tag(i), label))
+    }
+    /// Adds a swatch. Does nothing on a colormap legend.
+    pub fn entry<S: Into<String>>(mut self, color: Color, label: S) -> Self {
+        if let LegendKind::Categorical(ref mut entries) = self.kind {
+            entries.push((color, label.into()));
+        }
+        self
+    }
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    pub fn corner(mut self, corner: Corner) -> Self {
+        self.corner = corner;
+        self
+    }
+    pub fn element(self) -> Element {
+        Element {
+            shape: Shape::Legend(self),
+            msg: None,
+            attrs: vec![],
+            layer: None,
+            id: None,
+        }
+    }
+}
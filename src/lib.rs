@@ -1,6 +1,27 @@
 use serde::{ Serialize, Deserialize };
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+mod bounds;
+mod color;
+pub use color::Colormap;
+pub mod format;
+pub mod geometry;
+mod graph;
+pub use graph::{ Edge, Graph, GraphLayout, Layout, Node };
+mod lattice;
+mod legend;
+pub use legend::{ Corner, Legend, LegendKind };
+pub mod live;
+pub use live::LiveClient;
+pub use lattice::{ Grid, GridCell, Lattice, LatticeKind };
+mod validate;
+pub mod recorder;
+mod sampling;
+pub use sampling::{ Policy, Sampler };
+mod tracks;
+pub use tracks::TrackRecorder;
+pub use validate::{ Diagnostic, DiagnosticKind };
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
 pub struct Pos {
     pub x: f32,
     pub y: f32,
@@ -10,7 +31,7 @@ pub fn pos(x: f32, y: f32) -> Pos {
     Pos { x, y }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -18,6 +39,7 @@ pub struct Color {
     pub a: u8,
 }
 
+#[allow(clippy::excessive_precision)]
 const PHI_INV: f32 = 0.618033988749895;
 
 pub fn rgb_from_hsv((h, s, v): (f32, f32, f32)) -> [f32; 3] {
@@ -82,7 +104,7 @@ impl Color {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub struct Stroke {
     pub color: Color,
     pub width: f32,
@@ -127,6 +149,9 @@ impl Path {
         Element {
             shape: Shape::Path(self),
             msg: None,
+            attrs: vec![],
+            layer: None,
+            id: None,
         }
     }
 }
@@ -155,6 +180,9 @@ impl Circle {
         Element {
             shape: Shape::Circle(self),
             msg: None,
+            attrs: vec![],
+            layer: None,
+            id: None,
         }
     }
 }
@@ -179,6 +207,9 @@ impl Text {
         Element {
             shape: Shape::Text(self),
             msg: None,
+            attrs: vec![],
+            layer: None,
+            id: None,
         }
     }
 }
@@ -188,26 +219,75 @@ pub enum Shape {
     Path(Path),
     Circle(Circle),
     Text(Text),
+    Grid(Grid),
+    Legend(Legend),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Element {
     pub shape: Shape,
     pub msg: Option<String>,
+    /// Named numeric values the viewer can color the element by.
+    #[serde(default)]
+    pub attrs: Vec<(String, f32)>,
+    /// A category, e.g. "walls" or "agents", the viewer can filter by.
+    #[serde(default)]
+    pub layer: Option<String>,
+    /// An identifier of the thing drawn, e.g. the index of an agent, the viewer can filter by.
+    #[serde(default)]
+    pub id: Option<u64>,
+}
+
+pub(crate) fn set_attr(attrs: &mut Vec<(String, f32)>, name: String, value: f32) {
+    match attrs.iter_mut().find(|a| a.0 == name) {
+        Some(a) => a.1 = value,
+        None => attrs.push((name, value)),
+    }
 }
 
 impl Element {
+    /// An element without a msg, attrs, layer or id.
+    pub fn new(shape: Shape) -> Self {
+        Self { shape, msg: None, attrs: vec![], layer: None, id: None }
+    }
     pub fn with_msg<I: Into<String>>(mut self, msg: I) -> Self {
         self.msg = Some(msg.into());
         self
     }
+    /// Sets the attribute `name`, replacing an earlier value.
+    pub fn with_attr<S: Into<String>>(mut self, name: S, value: f32) -> Self {
+        set_attr(&mut self.attrs, name.into(), value);
+        self
+    }
+    pub fn attr(&self, name: &str) -> Option<f32> {
+        self.attrs.iter().find(|a| a.0 == name).map(|a| a.1)
+    }
+    pub fn with_layer<S: Into<String>>(mut self, layer: S) -> Self {
+        self.layer = Some(layer.into());
+        self
+    }
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
 }
 
+/// The elements drawn at one step, inside the rect spanned by `p1` and `p2`.
 #[derive(Serialize, Deserialize, Debug)]
+#[non_exhaustive]
 pub struct Frame {
     pub elems: Vec<Element>,
     pub p1: Pos,
     pub p2: Pos,
+    #[serde(default)]
+    pub keyframe: bool,
+    #[serde(default)]
+    pub step: Option<u64>,
+    /// Labels of the events that happened in this frame, shown as markers on the viewer's timeline.
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(skip)]
+    auto: Option<bounds::AutoBounds>,
 }
 
 impl Frame {
@@ -216,49 +296,132 @@ impl Frame {
             elems: vec![],
             p1,
             p2,
+            keyframe: false,
+            step: None,
+            events: vec![],
+            auto: None,
         }
     }
+    /// A frame with fixed bounds and the given elements.
+    pub fn from_elements(p1: Pos, p2: Pos, elems: Vec<Element>) -> Self {
+        Self { elems, ..Self::new(p1, p2) }
+    }
+    /// Marks this frame as a keyframe, which recording policies never drop.
+    pub fn keyframe(mut self) -> Self {
+        self.keyframe = true;
+        self
+    }
+    /// Sets the solver step of this frame, which places it on the time-series axis.
+    pub fn step(mut self, step: u64) -> Self {
+        self.step = Some(step);
+        self
+    }
+    /// Marks an event, e.g. "restart" or "best score updated", at this frame.
+    pub fn event<S: Into<String>>(mut self, label: S) -> Self {
+        self.events.push(label.into());
+        self
+    }
     pub fn add_element(mut self, elem: Element) -> Self {
+        self.push_element(elem);
+        self
+    }
+    pub fn push_element(&mut self, elem: Element) {
+        self.grow_bounds(&elem);
         self.elems.push(elem);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Track {
+    pub name: String,
+    pub frames: Vec<Frame>,
+}
+
+impl Track {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self { name: name.into(), frames: vec![] }
+    }
+    pub fn add_frame(mut self, frame: Frame) -> Self {
+        self.frames.push(frame);
         self
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Series {
+    pub name: String,
+    pub samples: Vec<(u64, f64)>,
+}
+
+impl Series {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self { name: name.into(), samples: vec![] }
+    }
+    pub fn add_sample(mut self, step: u64, value: f64) -> Self {
+        self.samples.push((step, value));
+        self
+    }
+}
+
+pub(crate) fn push_sample(series: &mut Vec<Series>, name: &str, step: u64, value: f64) {
+    match series.iter_mut().find(|s| s.name == name) {
+        Some(s) => s.samples.push((step, value)),
+        None => series.push(Series::new(name).add_sample(step, value)),
+    }
+}
+
+/// The frames of the main track, the named tracks and the time series of a recording.
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Frames {
     pub frames: Vec<Frame>,
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+    #[serde(default)]
+    pub series: Vec<Series>,
 }
 
 impl Frames {
     pub fn new() -> Self {
-        Self {
-            frames: vec![]
-        }
+        Self::default()
     }
     pub fn add_frame(mut self, frame: Frame) -> Self {
         self.frames.push(frame);
         self
     }
+    pub fn add_track(mut self, track: Track) -> Self {
+        self.tracks.push(track);
+        self
+    }
+    pub fn add_series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+    /// Appends a sample to the series called `name`, creating it if needed.
+    pub fn push_sample(&mut self, name: &str, step: u64, value: f64) {
+        push_sample(&mut self.series, name, step, value);
+    }
+    /// The frames of the main track followed by the frames of every named track.
+    pub fn all_frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter().chain(self.tracks.iter().flat_map(|t| t.frames.iter()))
+    }
 
+    /// The versioned encoding described in `format`.
     pub fn encode(&self) -> anyhow::Result<Vec<u8>> {
-        let encoded: Vec<u8> = bincode::serialize(self)?;
+        let mut encoded = vec![];
+        self.encode_to_writer(&mut encoded)?;
         Ok(encoded)
     }
 
+    /// Decodes the current encoding, or the baseline one written before it was versioned.
     pub fn decode(encoded: Vec<u8>) -> anyhow::Result<Self> {
-        let decoded: Self = bincode::deserialize(&encoded[..])?;
-        Ok(decoded)
+        Self::decode_from_reader(&encoded[..])
     }
 
     pub fn encode_to_file<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
-        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
-        bincode::serialize_into(writer, self)?;
-        Ok(())
+        self.encode_to_writer(std::io::BufWriter::new(std::fs::File::create(path)?))
     }
 
     pub fn decode_from_file<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
-        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
-        let res = bincode::deserialize_from(reader)?;
-        Ok(res)
+        Self::decode_from_reader(std::io::BufReader::new(std::fs::File::open(path)?))
     }
 }
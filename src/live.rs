@@ -0,0 +1,89 @@
+//! Streams frames to a running viewer over a local TCP or Unix domain socket, or into a pipe.
+//!
+//! The stream is the encoding described in `format`, written one packet at a time.
+
+use std::io::{ BufWriter, Write };
+use std::path::PathBuf;
+use anyhow::Context as _;
+use crate::*;
+use crate::format::PacketRef;
+pub use crate::format::{ read_packet, read_packets, write_packet, Packet };
+
+/// Where the viewer listens: `host:port`, or `unix:/path/to/socket` on Unix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    pub const DEFAULT: &'static str = "127.0.0.1:7878";
+
+    pub fn parse(s: &str) -> Self {
+        match s.trim().strip_prefix("unix:") {
+            Some(path) => Endpoint::Unix(PathBuf::from(path)),
+            None => Endpoint::Tcp(s.trim().to_owned()),
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{}", addr),
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Sends frames to a viewer listening on an `Endpoint`, e.g.
+/// `LiveClient::connect("127.0.0.1:7878")?.send_frame(frame)?`, or reading a pipe, e.g.
+/// `LiveClient::new(std::io::stderr())?` for `solver 2>&1 >/dev/null | visualizer -`.
+pub struct LiveClient {
+    writer: BufWriter<Box<dyn Write + Send>>,
+}
+
+impl LiveClient {
+    pub fn connect(endpoint: &str) -> anyhow::Result<Self> {
+        let endpoint = Endpoint::parse(endpoint);
+        let stream: Box<dyn Write + Send> = match endpoint {
+            Endpoint::Tcp(ref addr) => {
+                let stream = std::net::TcpStream::connect(addr).with_context(|| format!("connecting to {}", endpoint))?;
+                stream.set_nodelay(true)?;
+                Box::new(stream)
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(ref path) => Box::new(std::os::unix::net::UnixStream::connect(path).with_context(|| format!("connecting to {}", endpoint))?),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => anyhow::bail!("unix domain sockets are not supported on this platform"),
+        };
+        Self::new(stream)
+    }
+
+    /// Streams into `writer`, starting with the header of the encoding.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> anyhow::Result<Self> {
+        let mut writer = BufWriter::new(Box::new(writer) as Box<dyn Write + Send>);
+        format::write_header(&mut writer)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    fn send(&mut self, packet: &PacketRef<'_>) -> anyhow::Result<()> {
+        format::write_record(&mut self.writer, packet)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Sends a frame of the main track.
+    pub fn send_frame(&mut self, frame: Frame) -> anyhow::Result<()> {
+        self.send(&PacketRef::Frame { track: None, frame: &frame })
+    }
+
+    pub fn send_frame_to(&mut self, track: &str, frame: Frame) -> anyhow::Result<()> {
+        self.send(&PacketRef::Frame { track: Some(track), frame: &frame })
+    }
+
+    pub fn send_sample(&mut self, series: &str, step: u64, value: f64) -> anyhow::Result<()> {
+        self.send(&PacketRef::Sample { series, step, value })
+    }
+}
@@ -0,0 +1,295 @@
+//! A thread-local recorder behind the `vis_*!` macros, so a solver can record frames
+//! without passing a `Frames` value around.
+//!
+//! With the `disable-record` feature every macro expands to `()` without evaluating
+//! its arguments. Frames dropped by the recording `Policy` skip their element macros too.
+
+use std::cell::RefCell;
+use crate::*;
+
+thread_local! {
+    static RECORDER: RefCell<Sampler> = RefCell::new(Sampler::default());
+}
+
+pub fn with<R>(f: impl FnOnce(&mut Sampler) -> R) -> R {
+    RECORDER.with(|r| f(&mut r.borrow_mut()))
+}
+
+/// Sets the recording policy of this thread. The frames recorded so far are kept, thinned out
+/// as if the policy had been set from the start.
+pub fn set_policy(policy: Policy) {
+    with(|sampler| sampler.set_policy(policy));
+}
+
+pub fn new_frame(frame: Frame) {
+    with(|sampler| sampler.add_frame(frame));
+}
+
+/// Whether the current frame is kept by the policy, i.e. whether elements are worth building.
+pub fn recording() -> bool {
+    with(|sampler| sampler.offered() == 0 || sampler.is_current_kept())
+}
+
+/// Appends an element to the current frame, starting an auto-bounds frame if there is none.
+pub fn add_element(elem: Element) {
+    with(|sampler| {
+        if sampler.offered() == 0 {
+            sampler.add_frame(Frame::auto_bounds());
+        }
+        if let Some(frame) = sampler.current() {
+            frame.push_element(elem);
+        }
+    });
+}
+
+/// Sets the msg of the last element of the current frame.
+pub fn set_msg(msg: String) {
+    with(|sampler| {
+        if let Some(elem) = sampler.current().and_then(|f| f.elems.last_mut()) {
+            elem.msg = Some(msg);
+        }
+    });
+}
+
+/// Sets an attribute of the last element added to the current frame.
+pub fn set_attr(name: &str, value: f32) {
+    with(|sampler| {
+        if let Some(elem) = sampler.current().and_then(|f| f.elems.last_mut()) {
+            crate::set_attr(&mut elem.attrs, name.to_owned(), value);
+        }
+    });
+}
+
+/// Marks an event at the current frame, starting an auto-bounds frame if there is none.
+pub fn add_event(label: String) {
+    with(|sampler| {
+        if sampler.offered() == 0 {
+            sampler.add_frame(Frame::auto_bounds());
+        }
+        sampler.add_event(label);
+    });
+}
+
+/// Sets the layer of the last element added to the current frame.
+pub fn set_layer(layer: String) {
+    with(|sampler| {
+        if let Some(elem) = sampler.current().and_then(|f| f.elems.last_mut()) {
+            elem.layer = Some(layer);
+        }
+    });
+}
+
+/// Sets the id of the last element added to the current frame.
+pub fn set_id(id: u64) {
+    with(|sampler| {
+        if let Some(elem) = sampler.current().and_then(|f| f.elems.last_mut()) {
+            elem.id = Some(id);
+        }
+    });
+}
+
+/// Appends a sample to a time series at the step of the current frame.
+pub fn push_sample(name: &str, value: f64) {
+    with(|sampler| sampler.push_sample(name, value));
+}
+
+/// Takes the recorded frames, keeping the policy for the frames recorded afterwards.
+pub fn take() -> Frames {
+    with(|sampler| std::mem::replace(sampler, Sampler::new(sampler.policy())).into_frames())
+}
+
+pub fn flush_to_file<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<()> {
+    take().encode_to_file(path)
+}
+
+pub fn flush_to_stderr() -> anyhow::Result<()> {
+    take().encode_to_writer(std::io::BufWriter::new(std::io::stderr().lock()))
+}
+
+/// Starts a new frame: `vis_frame!()` for auto bounds, `vis_frame!(p1, p2)` for fixed bounds.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_frame {
+    () => { $crate::recorder::new_frame($crate::Frame::auto_bounds()) };
+    ($p1:expr, $p2:expr $(,)?) => { $crate::recorder::new_frame($crate::Frame::new($p1, $p2)) };
+}
+
+/// Starts a new keyframe, which the recording policy never drops.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_keyframe {
+    () => { $crate::recorder::new_frame($crate::Frame::auto_bounds().keyframe()) };
+    ($p1:expr, $p2:expr $(,)?) => { $crate::recorder::new_frame($crate::Frame::new($p1, $p2).keyframe()) };
+}
+
+/// Appends an `Element` to the current frame.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_elem {
+    ($elem:expr $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::add_element($elem)
+        }
+    };
+}
+
+/// `vis_path!(vertices, stroke(color, width), close(fill))`
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_path {
+    ($vp:expr $(, $m:ident($($a:expr),*))* $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::add_element($crate::Path::from_vertices(::std::convert::Into::into($vp))$(.$m($($a),*))*.element())
+        }
+    };
+}
+
+/// `vis_circle!(center, radius, fill(color), stroke(color, width))`
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_circle {
+    ($center:expr, $radius:expr $(, $m:ident($($a:expr),*))* $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::add_element($crate::Circle::new($center, $radius)$(.$m($($a),*))*.element())
+        }
+    };
+}
+
+/// `vis_text!(text, size, pos, color(color))`
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_text {
+    ($text:expr, $size:expr, $pos:expr $(, $m:ident($($a:expr),*))* $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::add_element($crate::Text::new($text, $size, $pos)$(.$m($($a),*))*.element())
+        }
+    };
+}
+
+/// Sets the msg of the last recorded element, with `format!` arguments.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_msg {
+    ($($arg:tt)*) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::set_msg(::std::format!($($arg)*))
+        }
+    };
+}
+
+/// Attaches a numeric attribute to the last element, e.g. `vis_attr!("cost", cost)`.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_attr {
+    ($name:expr, $value:expr $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::set_attr($name, $value as f32)
+        }
+    };
+}
+
+/// Puts the last element on a layer, e.g. `vis_layer!("walls")`.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_layer {
+    ($layer:expr $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::set_layer(::std::convert::Into::into($layer))
+        }
+    };
+}
+
+/// Tags the last element with an id, e.g. `vis_id!(agent)`. Panics if the id is negative or
+/// does not fit in a `u64`.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_id {
+    ($id:expr $(,)?) => {
+        if $crate::recorder::recording() {
+            $crate::recorder::set_id(::std::convert::TryFrom::try_from($id).expect("vis_id! needs an id that fits in a u64"))
+        }
+    };
+}
+
+/// Records a sample of a named time series, e.g. `vis_series!("score", score)`.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_series {
+    ($name:expr, $value:expr $(,)?) => { $crate::recorder::push_sample($name, $value as f64) };
+}
+
+/// Marks an event on the timeline at the current frame, e.g. `vis_event!("restart {}", n)`.
+/// Events outlive the frames the recording policy drops; only `Policy::AtMost` thins them out.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_event {
+    ($($arg:tt)*) => { $crate::recorder::add_event(::std::format!($($arg)*)) };
+}
+
+/// Writes the recorded frames to a file, or to stderr with `vis_flush!()`.
+#[cfg(not(feature = "disable-record"))]
+#[macro_export]
+macro_rules! vis_flush {
+    () => {
+        if let Err(e) = $crate::recorder::flush_to_stderr() {
+            ::std::eprintln!("vis_flush: {:?}", e);
+        }
+    };
+    ($path:expr $(,)?) => {
+        if let Err(e) = $crate::recorder::flush_to_file($path) {
+            ::std::eprintln!("vis_flush: {:?}", e);
+        }
+    };
+}
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_frame { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_keyframe { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_elem { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_path { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_circle { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_text { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_msg { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_attr { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_layer { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_id { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_series { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_event { ($($t:tt)*) => { () }; }
+
+#[cfg(feature = "disable-record")]
+#[macro_export]
+macro_rules! vis_flush { ($($t:tt)*) => { () }; }
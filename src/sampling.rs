@@ -0,0 +1,509 @@
+use std::time::{ Duration, Instant };
+use crate::*;
+
+/// Which of the offered frames a `Sampler` keeps. Keyframes are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Policy {
+    #[default]
+    All,
+    /// Keeps every k-th frame.
+    EveryK(u64),
+    /// Keeps at most n frames spread evenly over the run, halving the density as more arrive.
+    /// Every time series and the events are capped the same way, at n entries (at least one).
+    AtMost(usize),
+    /// Keeps a frame when at least this much wall-clock time passed since the last kept one.
+    Interval(Duration),
+}
+
+/// Collects frames under a `Policy`, so the output stays bounded however long the solver runs.
+#[derive(Debug)]
+pub struct Sampler {
+    policy: Policy,
+    frames: Vec<(u64, Frame)>,
+    series: Vec<Series>,
+    /// The thinning of `series`, by index.
+    series_strides: Vec<SeriesStride>,
+    /// Events with the offer index and the step of the frame they were marked at. They are attached
+    /// to the kept frames on output, so the policy can drop the frames they were marked at.
+    events: Vec<(u64, u64, String)>,
+    /// The thinning of `events`.
+    events_stride: SeriesStride,
+    next_step: u64,
+    /// The step of the last offered frame: its `Frame::step`, or else the number of frames offered before it.
+    step: u64,
+    stride: u64,
+    last_kept: Option<Instant>,
+    current_kept: bool,
+}
+
+/// Which samples of a series `Policy::AtMost` keeps: every `stride`-th of the ones offered.
+#[derive(Debug, Clone, Copy)]
+struct SeriesStride {
+    stride: u64,
+    offered: u64,
+}
+
+/// Keeps every other entry, doubling the stride.
+fn halve<T>(samples: &mut Vec<T>, stride: &mut SeriesStride) {
+    stride.stride *= 2;
+    let mut i = 0;
+    samples.retain(|_| {
+        i += 1;
+        i % 2 == 1
+    });
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new(Policy::All)
+    }
+}
+
+impl Sampler {
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            frames: vec![],
+            series: vec![],
+            series_strides: vec![],
+            events: vec![],
+            events_stride: SeriesStride { stride: 1, offered: 0 },
+            next_step: 0,
+            step: 0,
+            stride: 1,
+            last_kept: None,
+            current_kept: false,
+        }
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    fn wants_step(&self, step: u64) -> bool {
+        match self.policy {
+            Policy::All => true,
+            Policy::EveryK(k) => step % k.max(1) == 0,
+            Policy::AtMost(n) => n > 0 && step % self.stride == 0,
+            Policy::Interval(d) => self.last_kept.map_or(true, |t| t.elapsed() >= d),
+        }
+    }
+
+    /// Switches to `policy`, thinning out the frames kept so far as if it had been used from the start.
+    /// `Policy::Interval` keeps them all, since the times they were offered at are not known.
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = policy;
+        self.stride = 1;
+        match policy {
+            Policy::All | Policy::Interval(_) => {}
+            Policy::EveryK(k) => self.frames.retain(|(s, f)| f.keyframe || s % k.max(1) == 0),
+            Policy::AtMost(0) => self.frames.retain(|(_, f)| f.keyframe),
+            Policy::AtMost(n) => {
+                let fits = |stride: u64| self.frames.iter().filter(|(s, f)| !f.keyframe && s % stride == 0).count() <= n;
+                while !fits(self.stride) {
+                    self.stride *= 2;
+                }
+                let stride = self.stride;
+                self.frames.retain(|(s, f)| f.keyframe || s % stride == 0);
+            }
+        }
+        self.current_kept = self.frames.last().is_some_and(|&(s, _)| s + 1 == self.next_step);
+        for (series, stride) in self.series.iter_mut().zip(&mut self.series_strides) {
+            stride.stride = 1;
+            if let Policy::AtMost(n) = policy {
+                while series.samples.len() > n.max(1) {
+                    halve(&mut series.samples, stride);
+                }
+            }
+        }
+        self.events_stride.stride = 1;
+        if let Policy::AtMost(n) = policy {
+            while self.events.len() > n.max(1) {
+                halve(&mut self.events, &mut self.events_stride);
+            }
+        }
+    }
+
+    /// Whether the next offered frame would be kept.
+    pub fn wants(&self, keyframe: bool) -> bool {
+        keyframe || self.wants_step(self.next_step)
+    }
+
+    /// Halves the density until there is room for the frame at `step`, or it is no longer wanted.
+    fn decimate(&mut self, n: usize, step: u64) {
+        while step % self.stride == 0 && self.frames.iter().filter(|(_, f)| !f.keyframe).count() >= n {
+            self.stride *= 2;
+            let stride = self.stride;
+            self.frames.retain(|(s, f)| f.keyframe || s % stride == 0);
+        }
+    }
+
+    /// Offers the next frame, building it only if the policy keeps it.
+    pub fn add_frame_with(&mut self, keyframe: bool, f: impl FnOnce() -> Frame) {
+        let step = self.next_step;
+        self.next_step += 1;
+        self.step = step;
+        let mut keep = keyframe || self.wants_step(step);
+        if keep && !keyframe {
+            if let Policy::AtMost(n) = self.policy {
+                self.decimate(n, step);
+                keep = self.wants_step(step);
+            }
+        }
+        self.current_kept = keep;
+        if keep {
+            let mut frame = f();
+            self.step = *frame.step.get_or_insert(step);
+            for label in std::mem::take(&mut frame.events) {
+                self.push_event(step, label);
+            }
+            self.frames.push((step, frame));
+            if let Policy::Interval(_) = self.policy {
+                self.last_kept = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn add_frame(&mut self, mut frame: Frame) {
+        let keyframe = frame.keyframe;
+        let (offer, step) = (self.next_step, frame.step);
+        let events = std::mem::take(&mut frame.events);
+        self.add_frame_with(keyframe, move || frame);
+        // the events and the step of a frame the policy drops are kept all the same
+        self.step = step.unwrap_or(offer);
+        for label in events {
+            self.push_event(offer, label);
+        }
+    }
+
+    /// Keeps an event marked at the frame offered at `offer`, thinned out under `Policy::AtMost`.
+    fn push_event(&mut self, offer: u64, label: String) {
+        let index = self.events_stride.offered;
+        self.events_stride.offered += 1;
+        if let Policy::AtMost(n) = self.policy {
+            while index % self.events_stride.stride == 0 && self.events.len() >= n.max(1) {
+                halve(&mut self.events, &mut self.events_stride);
+            }
+            if index % self.events_stride.stride != 0 {
+                return;
+            }
+        }
+        self.events.push((offer, self.step, label));
+    }
+
+    /// The last offered frame, if the policy kept it.
+    pub fn current(&mut self) -> Option<&mut Frame> {
+        if self.current_kept { self.frames.last_mut().map(|(_, f)| f) } else { None }
+    }
+
+    pub fn is_current_kept(&self) -> bool {
+        self.current_kept
+    }
+
+    /// Marks an event at the last offered frame. If the policy drops the frame, the event moves to
+    /// the closest earlier kept frame, labelled with the step it was marked at. Only
+    /// `Policy::AtMost` drops events.
+    pub fn add_event(&mut self, label: String) {
+        self.push_event(self.next_step.saturating_sub(1), label);
+    }
+
+    /// The events of every kept frame, in the order of `frames`.
+    fn attached_events(&self) -> Vec<Vec<String>> {
+        let mut attached = vec![vec![]; self.frames.len()];
+        for (offer, step, label) in &self.events {
+            // the last kept frame at or before the event, or the first one for events before it
+            let i = self.frames.partition_point(|(s, _)| s <= offer).saturating_sub(1);
+            let Some((s, _)) = self.frames.get(i) else { break };
+            attached[i].push(if s == offer { label.clone() } else { format!("{} (step {})", label, step) });
+        }
+        attached
+    }
+
+    /// Calls `f` with every kept frame, its events attached as in `into_frames`, stopping at the
+    /// first error.
+    pub(crate) fn try_for_each_frame(&mut self, mut f: impl FnMut(&Frame) -> anyhow::Result<()>) -> anyhow::Result<()> {
+        let attached = self.attached_events();
+        for ((_, frame), mut events) in self.frames.iter_mut().zip(attached) {
+            events.extend(frame.events.iter().cloned());
+            std::mem::swap(&mut frame.events, &mut events);
+            let res = f(frame);
+            frame.events = events;
+            res?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn series(&self) -> &[Series] {
+        &self.series
+    }
+
+    /// Appends a sample at the step of the last offered frame, so it lines up with the frame on
+    /// the time-series axis. Samples are only dropped by `Policy::AtMost`, which thins every
+    /// series out like the frames.
+    pub fn push_sample(&mut self, name: &str, value: f64) {
+        self.push_sample_at(name, self.step, value);
+    }
+
+    /// Appends a sample at `step`, thinned like the ones from `push_sample`.
+    pub(crate) fn push_sample_at(&mut self, name: &str, step: u64, value: f64) {
+        let i = match self.series.iter().position(|s| s.name == name) {
+            Some(i) => i,
+            None => {
+                self.series.push(Series::new(name));
+                self.series_strides.push(SeriesStride { stride: 1, offered: 0 });
+                self.series.len() - 1
+            }
+        };
+        let (series, stride) = (&mut self.series[i], &mut self.series_strides[i]);
+        let index = stride.offered;
+        stride.offered += 1;
+        if let Policy::AtMost(n) = self.policy {
+            // as in `decimate`, with the index among the offered samples in place of the step
+            while index % stride.stride == 0 && series.samples.len() >= n.max(1) {
+                halve(&mut series.samples, stride);
+            }
+            if index % stride.stride != 0 {
+                return;
+            }
+        }
+        series.samples.push((step, value));
+    }
+
+    /// The number of frames offered so far, kept or not.
+    pub fn offered(&self) -> u64 {
+        self.next_step
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn into_frames(self) -> Frames {
+        let events = self.attached_events();
+        Frames {
+            frames: self.frames.into_iter().zip(events).map(|((_, mut f), e)| {
+                // ahead of events pushed through `current`
+                f.events.splice(0..0, e);
+                f
+            }).collect(),
+            series: self.series,
+            ..Frames::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Frame {
+        Frame::new(pos(0.0, 0.0), pos(1.0, 1.0))
+    }
+
+    /// The step and the events of every kept frame.
+    fn kept(sampler: Sampler) -> Vec<(u64, Vec<String>)> {
+        sampler.into_frames().frames.into_iter().map(|f| (f.step.unwrap(), f.events)).collect()
+    }
+
+    fn steps(sampler: Sampler) -> Vec<u64> {
+        kept(sampler).into_iter().map(|(s, _)| s).collect()
+    }
+
+    fn offer(sampler: &mut Sampler, n: usize) {
+        for _ in 0..n {
+            sampler.add_frame(frame());
+        }
+    }
+
+    #[test]
+    fn at_most_spreads_frames_evenly() {
+        let mut sampler = Sampler::new(Policy::AtMost(4));
+        offer(&mut sampler, 100);
+        assert_eq!(steps(sampler), vec![0, 32, 64, 96]);
+    }
+
+    #[test]
+    fn at_most_one_keeps_the_first_frame() {
+        let mut sampler = Sampler::new(Policy::AtMost(1));
+        offer(&mut sampler, 10);
+        assert_eq!(steps(sampler), vec![0]);
+    }
+
+    #[test]
+    fn at_most_zero_keeps_keyframes_only() {
+        let mut sampler = Sampler::new(Policy::AtMost(0));
+        offer(&mut sampler, 3);
+        sampler.add_frame(frame().keyframe());
+        assert_eq!(steps(sampler), vec![3]);
+    }
+
+    #[test]
+    fn keyframes_survive_decimation() {
+        let mut sampler = Sampler::new(Policy::AtMost(2));
+        for i in 0..20 {
+            sampler.add_frame(if i % 5 == 3 { frame().keyframe() } else { frame() });
+        }
+        assert_eq!(steps(sampler), vec![0, 3, 8, 13, 16, 18]);
+    }
+
+    #[test]
+    fn every_k_keeps_multiples_of_k() {
+        let mut sampler = Sampler::new(Policy::EveryK(3));
+        offer(&mut sampler, 10);
+        assert_eq!(steps(sampler), vec![0, 3, 6, 9]);
+    }
+
+    #[test]
+    fn every_zero_keeps_every_frame() {
+        let mut sampler = Sampler::new(Policy::EveryK(0));
+        offer(&mut sampler, 3);
+        assert_eq!(steps(sampler), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn interval_keeps_a_frame_once_the_time_passed() {
+        let mut sampler = Sampler::new(Policy::Interval(Duration::from_secs(3600)));
+        offer(&mut sampler, 5);
+        assert_eq!(steps(sampler), vec![0]);
+        let mut sampler = Sampler::new(Policy::Interval(Duration::ZERO));
+        offer(&mut sampler, 5);
+        assert_eq!(steps(sampler), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn dropped_frames_are_not_built() {
+        let mut sampler = Sampler::new(Policy::EveryK(2));
+        let mut built = 0;
+        for _ in 0..4 {
+            sampler.add_frame_with(false, || {
+                built += 1;
+                frame()
+            });
+        }
+        assert_eq!(built, 2);
+    }
+
+    #[test]
+    fn set_policy_thins_out_the_frames_kept_so_far() {
+        let mut sampler = Sampler::new(Policy::All);
+        offer(&mut sampler, 10);
+        sampler.set_policy(Policy::AtMost(3));
+        assert!(!sampler.is_current_kept());
+        offer(&mut sampler, 10);
+        assert_eq!(steps(sampler), vec![0, 8, 16]);
+    }
+
+    #[test]
+    fn set_policy_keeps_the_current_frame_when_it_survives() {
+        let mut sampler = Sampler::new(Policy::All);
+        offer(&mut sampler, 7);
+        sampler.set_policy(Policy::EveryK(3));
+        assert!(sampler.is_current_kept());
+        assert_eq!(steps(sampler), vec![0, 3, 6]);
+    }
+
+    fn samples(sampler: Sampler) -> Vec<u64> {
+        sampler.into_frames().series[0].samples.iter().map(|&(s, _)| s).collect()
+    }
+
+    #[test]
+    fn at_most_caps_every_series() {
+        let mut sampler = Sampler::new(Policy::AtMost(4));
+        for i in 0..100 {
+            sampler.add_frame(frame());
+            sampler.push_sample("x", i as f64);
+        }
+        assert_eq!(samples(sampler), vec![0, 32, 64, 96]);
+    }
+
+    #[test]
+    fn at_most_caps_samples_pushed_at_one_step() {
+        let mut sampler = Sampler::new(Policy::AtMost(3));
+        for i in 0..10 {
+            sampler.push_sample("x", i as f64);
+        }
+        let values: Vec<f64> = sampler.into_frames().series[0].samples.iter().map(|&(_, v)| v).collect();
+        assert_eq!(values, vec![0.0, 4.0, 8.0]);
+    }
+
+    #[test]
+    fn set_policy_thins_out_the_series() {
+        let mut sampler = Sampler::new(Policy::All);
+        for i in 0..10 {
+            sampler.add_frame(frame());
+            sampler.push_sample("x", i as f64);
+        }
+        sampler.set_policy(Policy::AtMost(3));
+        assert_eq!(samples(sampler), vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn events_do_not_pin_frames() {
+        let mut sampler = Sampler::new(Policy::AtMost(4));
+        for i in 0..100 {
+            sampler.add_frame(frame());
+            sampler.add_event(format!("e{}", i));
+        }
+        let kept = kept(sampler);
+        assert!(kept.len() <= 4);
+        assert_eq!(kept.iter().map(|(_, e)| e.len()).sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn at_most_caps_the_events() {
+        let mut sampler = Sampler::new(Policy::AtMost(3));
+        for i in 0..10 {
+            sampler.add_frame(frame().event(format!("f{}", i)));
+            sampler.add_event(format!("e{}", i));
+        }
+        let events = kept(sampler).into_iter().flat_map(|(_, e)| e).collect::<Vec<_>>();
+        assert_eq!(events, vec!["f0", "f4", "f8"]);
+        let mut sampler = Sampler::new(Policy::All);
+        for i in 0..10 {
+            sampler.add_frame(frame());
+            sampler.add_event(format!("e{}", i));
+        }
+        sampler.set_policy(Policy::AtMost(2));
+        let events = kept(sampler).into_iter().flat_map(|(_, e)| e).collect::<Vec<_>>();
+        assert_eq!(events, vec!["e0", "e8"]);
+    }
+
+    #[test]
+    fn samples_take_the_step_of_the_frame() {
+        let mut sampler = Sampler::new(Policy::EveryK(2));
+        for i in 0..4 {
+            sampler.add_frame(frame().step(100 + 10 * i));
+            sampler.push_sample("x", i as f64);
+            sampler.add_event(format!("e{}", i));
+        }
+        let frames = sampler.into_frames();
+        assert_eq!(frames.frames.iter().map(|f| f.step.unwrap()).collect::<Vec<_>>(), vec![100, 120]);
+        assert_eq!(frames.series[0].samples.iter().map(|&(s, _)| s).collect::<Vec<_>>(), vec![100, 110, 120, 130]);
+        assert_eq!(frames.frames[0].events, vec!["e0", "e1 (step 110)"]);
+    }
+
+    #[test]
+    fn events_of_dropped_frames_move_to_the_previous_kept_frame() {
+        let mut sampler = Sampler::new(Policy::EveryK(3));
+        for i in 0..5 {
+            sampler.add_frame(frame());
+            sampler.add_event(format!("e{}", i));
+        }
+        assert_eq!(kept(sampler), vec![
+            (0, vec!["e0".into(), "e1 (step 1)".into(), "e2 (step 2)".into()]),
+            (3, vec!["e3".into(), "e4 (step 4)".into()]),
+        ]);
+    }
+
+    #[test]
+    fn events_of_offered_frames_are_kept() {
+        let mut sampler = Sampler::new(Policy::EveryK(2));
+        sampler.add_frame(frame().event("a"));
+        sampler.add_frame(frame().event("b"));
+        assert_eq!(kept(sampler), vec![(0, vec!["a".into(), "b (step 1)".into()])]);
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::{ Mutex, MutexGuard };
+use crate::*;
+use crate::format::PacketRef;
+
+/// A `Send + Sync` recorder that keeps one track of frames per thread or per named worker.
+///
+/// Share it by reference with scoped threads or through an `Arc`, then write every track
+/// into one file with `encode_to_file`.
+#[derive(Debug, Default)]
+pub struct TrackRecorder {
+    policy: Policy,
+    tracks: Mutex<Vec<(String, Sampler)>>,
+}
+
+fn current_thread_name() -> String {
+    let thread = std::thread::current();
+    match thread.name() {
+        Some(name) => name.to_owned(),
+        None => format!("{:?}", thread.id()),
+    }
+}
+
+/// The name of a track's series, or of a track added to it with `add_frames`, in the output.
+fn series_name(track: &str, series: &str) -> String {
+    format!("{}: {}", track, series)
+}
+
+impl TrackRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A recorder that samples every track with `policy`.
+    pub fn with_policy(policy: Policy) -> Self {
+        Self { policy, ..Self::default() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<(String, Sampler)>> {
+        self.tracks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn with_track<R>(&self, name: &str, f: impl FnOnce(&mut Sampler) -> R) -> R {
+        let mut tracks = self.lock();
+        let idx = match tracks.iter().position(|(n, _)| n == name) {
+            Some(idx) => idx,
+            None => {
+                tracks.push((name.to_owned(), Sampler::new(self.policy)));
+                tracks.len() - 1
+            }
+        };
+        f(&mut tracks[idx].1)
+    }
+
+    /// Appends a frame to the track of the current thread, named after the thread.
+    pub fn add_frame(&self, frame: Frame) {
+        self.add_frame_to(&current_thread_name(), frame);
+    }
+
+    pub fn add_frame_to(&self, name: &str, frame: Frame) {
+        self.with_track(name, |t| t.add_frame(frame));
+    }
+
+    /// Appends a sample to a time series of the current thread's track.
+    pub fn push_sample(&self, name: &str, value: f64) {
+        self.with_track(&current_thread_name(), |t| t.push_sample(name, value));
+    }
+
+    pub fn push_sample_to(&self, track: &str, name: &str, value: f64) {
+        self.with_track(track, |t| t.push_sample(name, value));
+    }
+
+    /// Offers frames recorded elsewhere, e.g. with `recorder::take()` on a worker thread. Their
+    /// time series go with the track, and their tracks become tracks named `"name: track"`.
+    pub fn add_frames(&self, name: &str, frames: Frames) {
+        self.with_track(name, |t| {
+            frames.frames.into_iter().for_each(|f| t.add_frame(f));
+            for s in frames.series {
+                for (step, value) in s.samples {
+                    t.push_sample_at(&s.name, step, value);
+                }
+            }
+        });
+        for track in frames.tracks {
+            self.add_frames(&series_name(name, &track.name), Frames { frames: track.frames, ..Frames::new() });
+        }
+    }
+
+    /// Takes the recorded tracks, leaving empty tracks behind. The time series of a
+    /// track are prefixed with its name.
+    pub fn take(&self) -> Frames {
+        let mut tracks = self.lock();
+        let mut res = Frames::new();
+        for (name, sampler) in tracks.drain(..) {
+            let frames = sampler.into_frames();
+            res.series.extend(frames.series.into_iter().map(|s| Series {
+                name: series_name(&name, &s.name),
+                ..s
+            }));
+            res.tracks.push(Track { name, frames: frames.frames });
+        }
+        res
+    }
+
+    pub fn into_frames(self) -> Frames {
+        self.take()
+    }
+
+    /// Writes all tracks into one file, laid out like `take().encode_to_file(path)`, and clears
+    /// them once the file is written. Recording threads wait until then.
+    pub fn encode_to_file<P: AsRef<std::path::Path>>(&self, path: P) -> anyhow::Result<()> {
+        let mut tracks = self.lock();
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        format::write_header(&mut writer)?;
+        for (name, sampler) in tracks.iter_mut() {
+            sampler.try_for_each_frame(|frame| format::write_record(&mut writer, &PacketRef::Frame { track: Some(name), frame }))?;
+        }
+        for (name, sampler) in tracks.iter() {
+            for s in sampler.series() {
+                let series = Series { name: series_name(name, &s.name), samples: s.samples.clone() };
+                format::write_record(&mut writer, &PacketRef::Series(&series))?;
+            }
+        }
+        std::io::Write::flush(&mut writer)?;
+        tracks.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: f32) -> Frame {
+        Frame::new(pos(0.0, 0.0), pos(1.0, 1.0)).add_element(Circle::new(pos(x, 0.0), 1.0).element())
+    }
+
+    #[test]
+    fn threads_record_into_their_own_tracks() {
+        let recorder = TrackRecorder::new();
+        std::thread::scope(|scope| {
+            for name in ["a", "b"] {
+                let recorder = &recorder;
+                std::thread::Builder::new().name(name.to_owned()).spawn_scoped(scope, move || {
+                    for i in 0..3 {
+                        recorder.add_frame(frame(i as f32));
+                        recorder.push_sample("x", i as f64);
+                    }
+                }).unwrap();
+            }
+        });
+        let mut frames = recorder.take();
+        frames.tracks.sort_by(|a, b| a.name.cmp(&b.name));
+        frames.series.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(frames.tracks.iter().map(|t| (t.name.as_str(), t.frames.len())).collect::<Vec<_>>(), vec![("a", 3), ("b", 3)]);
+        assert_eq!(frames.series.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["a: x", "b: x"]);
+        assert_eq!(frames.series[0].samples, vec![(0, 0.0), (1, 1.0), (2, 2.0)]);
+        assert!(recorder.take().tracks.is_empty());
+    }
+
+    #[test]
+    fn frames_taken_on_a_worker_keep_their_series() {
+        let recorder = TrackRecorder::new();
+        let worker = std::thread::spawn(|| {
+            for i in 0..4 {
+                recorder::new_frame(frame(i as f32));
+                recorder::push_sample("score", i as f64 * 10.0);
+            }
+            recorder::take()
+        });
+        recorder.add_frames("worker", worker.join().unwrap());
+        let frames = recorder.take();
+        assert_eq!(frames.tracks.len(), 1);
+        assert_eq!(frames.tracks[0].name, "worker");
+        assert_eq!(frames.tracks[0].frames.iter().map(|f| f.step).collect::<Vec<_>>(), vec![Some(0), Some(1), Some(2), Some(3)]);
+        assert_eq!(frames.series.len(), 1);
+        assert_eq!(frames.series[0].name, "worker: score");
+        assert_eq!(frames.series[0].samples, vec![(0, 0.0), (1, 10.0), (2, 20.0), (3, 30.0)]);
+    }
+
+    #[test]
+    fn added_tracks_are_nested_under_the_name() {
+        let recorder = TrackRecorder::new();
+        recorder.add_frames("w", Frames::new().add_frame(frame(0.0)).add_track(Track::new("inner").add_frame(frame(1.0))));
+        let names = recorder.take().tracks.into_iter().map(|t| t.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["w", "w: inner"]);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    NonFiniteCoordinate,
+    DegenerateFrameRect,
+    ShortPath { vertices: usize },
+    NonPositiveRadius { radius: f32 },
+    NonPositiveCellSize { size: f32 },
+    TransparentWithMsg,
+    EmptyFrame,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub track: Option<String>,
+    pub frame: usize,
+    pub element: Option<usize>,
+    pub kind: DiagnosticKind,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref track) = self.track {
+            write!(f, "track {}, ", track)?;
+        }
+        match self.element {
+            Some(e) => write!(f, "frame {}, element {}: ", self.frame, e)?,
+            None => write!(f, "frame {}: ", self.frame)?,
+        }
+        match self.kind {
+            DiagnosticKind::NonFiniteCoordinate => write!(f, "NaN or infinite coordinate, check the value passed to pos()"),
+            DiagnosticKind::DegenerateFrameRect => write!(f, "frame rect has p1 == p2, give Frame::new two distinct corners"),
+            DiagnosticKind::ShortPath { vertices } => write!(f, "path has {} vertices, a path needs at least two", vertices),
+            DiagnosticKind::NonPositiveRadius { radius } => write!(f, "circle radius is {}, it must be positive", radius),
+            DiagnosticKind::NonPositiveCellSize { size } => write!(f, "lattice cell size is {}, it must be positive", size),
+            DiagnosticKind::TransparentWithMsg => write!(f, "element is fully transparent but has a msg, it can not be seen"),
+            DiagnosticKind::EmptyFrame => write!(f, "frame has no elements"),
+        }
+    }
+}
+
+fn finite(p: &Pos) -> bool {
+    p.x.is_finite() && p.y.is_finite()
+}
+
+fn visible(c: &Color) -> bool {
+    c.a > 0
+}
+
+fn stroke_visible(s: &Stroke) -> bool {
+    visible(&s.color) && s.width > 0.0
+}
+
+fn check_element(elem: &Element) -> Vec<DiagnosticKind> {
+    let mut res = vec![];
+    let (coords_ok, transparent) = match elem.shape {
+        Shape::Path(ref p) => {
+            if p.vp.len() < 2 {
+                res.push(DiagnosticKind::ShortPath { vertices: p.vp.len() });
+            }
+            (
+                p.vp.iter().all(finite) && p.stroke.width.is_finite(),
+                !p.fill.as_ref().is_some_and(visible) && !stroke_visible(&p.stroke),
+            )
+        }
+        Shape::Circle(ref c) => {
+            if c.radius.is_finite() && c.radius <= 0.0 {
+                res.push(DiagnosticKind::NonPositiveRadius { radius: c.radius });
+            }
+            (
+                finite(&c.center) && c.radius.is_finite(),
+                !c.fill.as_ref().is_some_and(visible) && !c.stroke.as_ref().is_some_and(stroke_visible),
+            )
+        }
+        Shape::Text(ref t) => {
+            (finite(&t.pos) && t.size.is_finite(), !visible(&t.color))
+        }
+        Shape::Grid(ref g) => {
+            if g.lattice.size.is_finite() && g.lattice.size <= 0.0 {
+                res.push(DiagnosticKind::NonPositiveCellSize { size: g.lattice.size });
+            }
+            // the viewer always shows the cell coordinates, so a grid is never invisible to hovers
+            (finite(&g.lattice.origin) && g.lattice.size.is_finite() && g.stroke.width.is_finite(), false)
+        }
+        Shape::Legend(_) => (true, false),
+    };
+    if !coords_ok {
+        res.push(DiagnosticKind::NonFiniteCoordinate);
+    }
+    if transparent && elem.msg.is_some() {
+        res.push(DiagnosticKind::TransparentWithMsg);
+    }
+    res
+}
+
+impl Frame {
+    pub fn validate(&self, frame: usize) -> Vec<Diagnostic> {
+        let mut res = vec![];
+        if !finite(&self.p1) || !finite(&self.p2) {
+            res.push(Diagnostic { track: None, frame, element: None, kind: DiagnosticKind::NonFiniteCoordinate });
+        }
+        else if self.p1.x == self.p2.x && self.p1.y == self.p2.y {
+            res.push(Diagnostic { track: None, frame, element: None, kind: DiagnosticKind::DegenerateFrameRect });
+        }
+        if self.elems.is_empty() {
+            res.push(Diagnostic { track: None, frame, element: None, kind: DiagnosticKind::EmptyFrame });
+        }
+        for (i, elem) in self.elems.iter().enumerate() {
+            res.extend(check_element(elem).into_iter().map(|kind| Diagnostic { track: None, frame, element: Some(i), kind }));
+        }
+        res
+    }
+}
+
+impl Frames {
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut res: Vec<_> = self.frames.iter().enumerate().flat_map(|(i, frame)| frame.validate(i)).collect();
+        for track in self.tracks.iter() {
+            res.extend(track.frames.iter().enumerate().flat_map(|(i, frame)| frame.validate(i)).map(|d| Diagnostic {
+                track: Some(track.name.clone()),
+                ..d
+            }));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line() -> Element {
+        Path::new().add_pos(pos(0.0, 0.0)).add_pos(pos(1.0, 1.0)).element()
+    }
+
+    fn frame(elem: Element) -> Frame {
+        Frame::new(pos(0.0, 0.0), pos(1.0, 1.0)).add_element(elem)
+    }
+
+    fn kinds(frame: &Frame) -> Vec<DiagnosticKind> {
+        frame.validate(0).into_iter().map(|d| d.kind).collect()
+    }
+
+    /// Where each diagnostic points: the frame and the element.
+    fn places(diagnostics: Vec<Diagnostic>) -> Vec<(usize, Option<usize>)> {
+        diagnostics.into_iter().map(|d| (d.frame, d.element)).collect()
+    }
+
+    #[test]
+    fn valid_frames_have_no_diagnostics() {
+        assert!(frame(line()).validate(0).is_empty());
+        assert!(frame(Circle::new(pos(0.0, 0.0), 1.0).fill(Color::new(255, 0, 0)).element().with_msg("c")).validate(0).is_empty());
+    }
+
+    #[test]
+    fn non_finite_coordinates() {
+        let f = frame(Circle::new(pos(f32::NAN, 0.0), 1.0).element());
+        assert_eq!(kinds(&f), vec![DiagnosticKind::NonFiniteCoordinate]);
+        assert_eq!(places(f.validate(3)), vec![(3, Some(0))]);
+        let f = Frame::new(pos(0.0, 0.0), pos(f32::INFINITY, 1.0)).add_element(line());
+        assert_eq!(kinds(&f), vec![DiagnosticKind::NonFiniteCoordinate]);
+        assert_eq!(places(f.validate(0)), vec![(0, None)]);
+        assert_eq!(kinds(&frame(Text::new("t", f32::NAN, pos(0.0, 0.0)).element())), vec![DiagnosticKind::NonFiniteCoordinate]);
+    }
+
+    #[test]
+    fn degenerate_frame_rects() {
+        let f = Frame::new(pos(1.0, 2.0), pos(1.0, 2.0)).add_element(line());
+        assert_eq!(kinds(&f), vec![DiagnosticKind::DegenerateFrameRect]);
+    }
+
+    #[test]
+    fn short_paths() {
+        assert_eq!(kinds(&frame(Path::new().add_pos(pos(0.0, 0.0)).element())), vec![DiagnosticKind::ShortPath { vertices: 1 }]);
+        assert_eq!(kinds(&frame(Path::new().element())), vec![DiagnosticKind::ShortPath { vertices: 0 }]);
+    }
+
+    #[test]
+    fn non_positive_radii() {
+        assert_eq!(kinds(&frame(Circle::new(pos(0.0, 0.0), 0.0).element())), vec![DiagnosticKind::NonPositiveRadius { radius: 0.0 }]);
+        assert_eq!(kinds(&frame(Circle::new(pos(0.0, 0.0), -2.0).element())), vec![DiagnosticKind::NonPositiveRadius { radius: -2.0 }]);
+        // reported as a non-finite coordinate only
+        assert_eq!(kinds(&frame(Circle::new(pos(0.0, 0.0), f32::NEG_INFINITY).element())), vec![DiagnosticKind::NonFiniteCoordinate]);
+    }
+
+    #[test]
+    fn non_positive_cell_sizes() {
+        let grid = Grid::new(Lattice::isometric(pos(0.0, 0.0), -1.0)).add_cell(GridCell::new(0, 0));
+        assert_eq!(kinds(&frame(grid.element())), vec![DiagnosticKind::NonPositiveCellSize { size: -1.0 }]);
+    }
+
+    #[test]
+    fn transparent_elements_with_a_msg() {
+        let clear = || Color::newa(0, 0, 0, 0);
+        let text = Text::new("t", 1.0, pos(0.0, 0.0)).color(clear()).element();
+        assert_eq!(kinds(&frame(text.with_msg("hidden"))), vec![DiagnosticKind::TransparentWithMsg]);
+        let path = Path::from_vertices(vec![pos(0.0, 0.0), pos(1.0, 0.0)]).stroke(Color::new(0, 0, 0), 0.0).element();
+        assert_eq!(kinds(&frame(path.with_msg("hidden"))), vec![DiagnosticKind::TransparentWithMsg]);
+        // a circle without fill or stroke
+        assert_eq!(kinds(&frame(Circle::new(pos(0.0, 0.0), 1.0).element().with_msg("hidden"))), vec![DiagnosticKind::TransparentWithMsg]);
+        // without a msg there is nothing to report
+        assert!(frame(Text::new("t", 1.0, pos(0.0, 0.0)).color(clear()).element()).validate(0).is_empty());
+    }
+
+    #[test]
+    fn empty_frames() {
+        assert_eq!(kinds(&Frame::new(pos(0.0, 0.0), pos(1.0, 1.0))), vec![DiagnosticKind::EmptyFrame]);
+    }
+
+    #[test]
+    fn frames_number_their_diagnostics() {
+        let frames = Frames::new()
+            .add_frame(frame(line()))
+            .add_frame(Frame::new(pos(0.0, 0.0), pos(1.0, 1.0)))
+            .add_frame(frame(Path::new().element()));
+        assert_eq!(places(frames.validate()), vec![(1, None), (2, Some(0))]);
+    }
+
+    #[test]
+    fn tracks_name_their_diagnostics() {
+        let frames = Frames::new()
+            .add_frame(frame(line()))
+            .add_track(Track::new("t").add_frame(frame(line())).add_frame(Frame::new(pos(0.0, 0.0), pos(1.0, 1.0))));
+        assert_eq!(frames.validate(), vec![Diagnostic { track: Some("t".into()), frame: 1, element: None, kind: DiagnosticKind::EmptyFrame }]);
+    }
+}
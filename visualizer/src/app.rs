@@ -1,57 +1,727 @@
+mod bookmarks;
+mod chart;
+mod coloring;
+mod filter;
+mod inspector;
+mod legend;
+mod live;
+mod measure;
 mod parser;
+mod playback;
+mod search;
+mod session;
+mod stack;
+mod timeline;
 mod transform;
-
-use std::path::PathBuf;
+mod watch;
 
 use eframe::{egui::*};
-use epaint::*;
 
-use parser::PaintFrame;
+use parser::{ PaintData, PaintFrame, PaintTrack };
+use visualizer_shapes::{ Diagnostic, Series };
+use visualizer_shapes::live::Packet;
 
 use parser::ElementKind;
 
+use std::path::{ Path, PathBuf };
 use std::sync::mpsc::{ channel, Receiver, Sender };
 
+/// The contents of a picked file, with its path where the platform gives one.
+type PickedFile = (Option<PathBuf>, Vec<u8>);
+
 pub struct EguiSample {
     frame_idx: usize,
-    selected_file: (Sender<Vec<u8>>, Receiver<Vec<u8>>),
+    track_idx: usize,
+    side_by_side: bool,
+    selected_file: (Sender<PickedFile>, Receiver<PickedFile>),
+    #[allow(dead_code)]
     paint_str: String,
-    frames: Vec<PaintFrame>,
+    selected_session: (Sender<PickedFile>, Receiver<PickedFile>),
+    watch: watch::Watch,
+    /// Recently opened files, most recent first.
+    recent: Vec<PathBuf>,
+    tracks: Vec<PaintTrack>,
+    series: Vec<Series>,
+    chart: chart::Chart,
+    attrs: Vec<String>,
+    layers: Vec<String>,
+    color_by: coloring::ColorBy,
+    filter: filter::Filter,
+    playback: playback::Playback,
+    live: live::Live,
+    bookmarks: bookmarks::Bookmarks,
+    search: search::Search,
+    inspector: inspector::Inspector,
+    stack: stack::Stack,
+    measure: measure::Measure,
+    /// The track, screen rect and transform of each canvas shown in the last update.
+    canvases: Vec<(usize, Rect, emath::RectTransform)>,
+    /// The element clicked, or found by a search, outlined on the canvas.
+    selected: Option<inspector::ElementRef>,
+    diagnostics: Vec<Diagnostic>,
     msg: String,
     drag_pos: Option<Pos2>,
     frame_rect: Rect,
+    /// The frame `--frame` asked for while reading stdin, shown once it arrives.
+    pending_frame: Option<usize>,
+    /// Keeps the first frame arriving from replacing the view `--view` asked for.
+    fixed_view: bool,
 }
 
 impl EguiSample {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        Self::restored(cc, true)
+    }
+
+    /// Starts with the settings of the last run, and its file if `reopen`.
+    fn restored(cc: &eframe::CreationContext<'_>, reopen: bool) -> Self {
+        let mut app = Self {
             frame_idx: 0,
+            track_idx: 0,
+            side_by_side: false,
             selected_file: channel(),
-            paint_str: format!("# (-20, -20) (250, 300)\nr (100, 100) (200, 200) {{{{rect}}}}\nr (0, 0) (50, 50) {{{{rect2}}}}\n"),
-            frames: vec![],
+            paint_str: "# (-20, -20) (250, 300)\nr (100, 100) (200, 200) {{rect}}\nr (0, 0) (50, 50) {{rect2}}\n".to_owned(),
+            selected_session: channel(),
+            watch: watch::Watch::default(),
+            recent: vec![],
+            tracks: vec![],
+            series: vec![],
+            chart: chart::Chart::default(),
+            attrs: vec![],
+            layers: vec![],
+            color_by: coloring::ColorBy::default(),
+            filter: filter::Filter::default(),
+            playback: playback::Playback::default(),
+            live: live::Live::default(),
+            bookmarks: bookmarks::Bookmarks::default(),
+            search: search::Search::default(),
+            inspector: inspector::Inspector::default(),
+            stack: stack::Stack::default(),
+            measure: measure::Measure::default(),
+            canvases: vec![],
+            selected: None,
+            diagnostics: vec![],
             msg: String::new(),
             drag_pos: None,
             frame_rect: Rect::from_min_max(pos2(0.0, 0.0), pos2(100.0, 100.0)),
+            pending_frame: None,
+            fixed_view: false,
+        };
+        if let Some(mut session) = cc.storage.and_then(|s| eframe::get_value::<session::Session>(s, eframe::APP_KEY)) {
+            if !reopen {
+                session.path = None;
+            }
+            app.recent = std::mem::take(&mut session.recent);
+            app.restore(session);
+        }
+        app
+    }
+
+    /// Starts on the file, frame and view given on the command line.
+    pub fn with_args(cc: &eframe::CreationContext<'_>, args: crate::Args) -> Self {
+        let mut app = Self::restored(cc, args.path.is_none());
+        if args.path.as_deref() == Some(Path::new("-")) {
+            app.live.read_stdin(&cc.egui_ctx);
+            app.pending_frame = args.frame.filter(|&i| i > 0);
+        }
+        else if let Some(path) = args.path {
+            app.open(path);
+        }
+        if args.watch {
+            app.watch.enabled = true;
+        }
+        if let Some(frame) = args.frame {
+            app.frame_idx = frame.min(app.frame_count().saturating_sub(1));
+        }
+        if let Some([x0, y0, x1, y1]) = args.view {
+            app.frame_rect = Rect::from_min_max(pos2(x0, y0), pos2(x1, y1));
+            app.fixed_view = true;
+        }
+        app
+    }
+
+    /// The state `save` stores, and the next start restores.
+    fn session(&self) -> session::Session {
+        session::Session {
+            path: self.watch.path().map(Path::to_owned),
+            recent: self.recent.clone(),
+            track: self.tracks.get(self.track_idx).and_then(|t| t.name.clone()),
+            frame_idx: self.frame_idx,
+            view: Some(self.frame_rect),
+            side_by_side: self.side_by_side,
+            watch: self.watch.enabled,
+            playback: self.playback.clone(),
+            color_by: self.color_by.clone(),
+            filter: self.filter.clone(),
+            show_all_hits: self.stack.enabled,
+            live_address: Some(self.live.address.clone()),
+            live_follow: self.live.follow,
+            bookmarks: self.bookmarks.list.clone(),
+        }
+    }
+
+    /// Applies the settings of `session`, then opens its file on the same track, frame and view.
+    /// Without a file, the frame and the view apply to the data already loaded.
+    fn restore(&mut self, session: session::Session) {
+        self.side_by_side = session.side_by_side;
+        self.watch.enabled = session.watch;
+        self.playback = session.playback;
+        self.color_by = session.color_by;
+        self.filter = session.filter;
+        self.stack.enabled = session.show_all_hits;
+        if let Some(address) = session.live_address {
+            self.live.address = address;
+        }
+        self.live.follow = session.live_follow;
+        self.bookmarks.list = session.bookmarks;
+        if let Some(path) = session.path {
+            if !self.open(path) {
+                return;
+            }
+        }
+        self.go_to(session.track, session.frame_idx, session.view);
+    }
+
+    fn go_to(&mut self, track: Option<String>, frame_idx: usize, view: Option<Rect>) {
+        if let Some(i) = self.tracks.iter().position(|t| t.name == track) {
+            self.track_idx = i;
+        }
+        self.frame_idx = frame_idx.min(self.frame_count().saturating_sub(1));
+        if let Some(view) = view.filter(|r| r.is_finite() && r.is_positive()) {
+            self.frame_rect = view;
+        }
+    }
+
+    /// Jumps to the frame of a diagnostic, revealing its element if it names one.
+    fn show_diagnostic(&mut self, ctx: &Context, d: Diagnostic) {
+        let track = self.tracks.iter().position(|t| t.name == d.track).unwrap_or(self.track_idx);
+        let elem = d.element.and_then(|e| {
+            self.tracks.get(track)?.frames.get(d.frame)?.elems.iter().position(|x| x.source_index == e)
+        });
+        match elem {
+            Some(elem) => self.reveal(ctx, (track, d.frame, elem)),
+            None => {
+                self.track_idx = track;
+                self.frame_idx = d.frame;
+            }
+        }
+    }
+
+    /// Jumps to an element and selects it, moving the view to it.
+    fn reveal(&mut self, ctx: &Context, (track, frame, elem): inspector::ElementRef) {
+        self.track_idx = track;
+        self.frame_idx = frame;
+        self.selected = Some((track, frame, elem));
+        let Some(e) = self.tracks.get(track).and_then(|t| t.frames.get(frame)).and_then(|f| f.elems.get(elem)) else { return };
+        let bounds = ctx.fonts(|f| e.bounds(f));
+        if !bounds.is_finite() {
+            return;
+        }
+        let size = self.frame_rect.size();
+        self.frame_rect = if bounds.width() > size.x || bounds.height() > size.y {
+            fit_aspect(bounds.expand(bounds.size().max_elem() * 0.1), size)
+        }
+        else {
+            Rect::from_center_size(bounds.center(), size)
+        };
+    }
+
+    /// Restores a session file, read from `path` if it has one, which goes on the recent list.
+    fn open_session(&mut self, path: Option<&Path>, bytes: &[u8]) {
+        match session::Session::from_ron(bytes) {
+            Ok(session) => {
+                self.restore(match path.and_then(Path::parent) {
+                    Some(dir) => session.resolve(dir),
+                    None => session,
+                });
+                if let Some(path) = path {
+                    session::add_recent(&mut self.recent, path.to_owned());
+                }
+            }
+            Err(e) => self.msg = format!("invalid session file: {:?}", e),
+        }
+    }
+
+    /// The frames of the selected track.
+    fn frames(&self) -> &[PaintFrame] {
+        self.tracks.get(self.track_idx).map_or(&[], |t| &t.frames[..])
+    }
+
+    /// The events of the selected track and its bookmarks, by frame.
+    fn markers(&self) -> Vec<timeline::Marker> {
+        let name = self.tracks.get(self.track_idx).and_then(|t| t.name.clone());
+        let mut markers = self.frames().iter().enumerate()
+            .flat_map(|(i, f)| f.events.iter().map(move |e| timeline::Marker { frame: i, label: e.clone(), bookmark: false }))
+            .collect::<Vec<_>>();
+        markers.extend(self.bookmarks.list.iter()
+            .filter(|b| b.track == name)
+            .map(|b| timeline::Marker { frame: b.frame, label: b.name.clone(), bookmark: true }));
+        markers.sort_by_key(|m| m.frame);
+        markers
+    }
+
+    /// The number of frames the slider scrubs through. Side by side, shorter tracks
+    /// keep showing their last frame.
+    fn frame_count(&self) -> usize {
+        if self.side_by_side {
+            self.tracks.iter().map(|t| t.frames.len()).max().unwrap_or(0)
+        }
+        else {
+            self.frames().len()
+        }
+    }
+
+    fn load(&mut self, data: PaintData) {
+        self.tracks = data.tracks;
+        self.series = data.series;
+        self.chart = chart::Chart::default();
+        self.attrs = data.attrs;
+        self.layers = data.layers;
+        self.color_by.invalidate();
+        self.search.clear();
+        self.selected = None;
+        self.diagnostics = data.diagnostics;
+        self.frame_idx = 0;
+        self.track_idx = 0;
+        if let Some(frame) = self.frames().first() {
+            self.frame_rect = viewable_rect(frame.rect);
+        }
+    }
+
+    /// Replaces the data after the file changed, keeping the frame and the view when
+    /// the new data still has that frame with the same bounds.
+    fn reload(&mut self, data: PaintData) {
+        let (frame_idx, frame_rect) = (self.frame_idx, self.frame_rect);
+        let track = self.tracks.get(self.track_idx).map(|t| t.name.clone());
+        let old_rect = self.frames().get(frame_idx).map(|f| f.rect);
+        self.load(data);
+        if let Some(i) = track.and_then(|name| self.tracks.iter().position(|t| t.name == name)) {
+            self.track_idx = i;
+        }
+        if frame_idx < self.frame_count() {
+            self.frame_idx = frame_idx;
+        }
+        let new_rect = self.frames().get(self.frame_idx).map(|f| f.rect);
+        if old_rect.is_some() && new_rect == old_rect {
+            self.frame_rect = frame_rect;
+        }
+        else if let Some(rect) = new_rect {
+            self.frame_rect = viewable_rect(rect);
+        }
+    }
+
+    /// Appends a frame or a sample streamed by a `LiveClient`.
+    fn append(&mut self, packet: Packet) {
+        match packet {
+            Packet::Frame { track, frame } => {
+                let idx = match self.tracks.iter().position(|t| t.name == track) {
+                    Some(i) => i,
+                    None => {
+                        self.tracks.push(PaintTrack { name: track, frames: vec![] });
+                        self.tracks.len() - 1
+                    }
+                };
+                let n = self.tracks[idx].frames.len();
+                let name = &self.tracks[idx].name;
+                self.diagnostics.extend(frame.validate(n).into_iter().map(|d| Diagnostic { track: name.clone(), ..d }));
+                let frame = PaintFrame::from_frame(frame, n);
+                for (attr, _) in frame.elems.iter().flat_map(|e| e.attrs.iter()) {
+                    if let Err(i) = self.attrs.binary_search(attr) {
+                        self.attrs.insert(i, attr.clone());
+                    }
+                }
+                for layer in frame.elems.iter().filter_map(|e| e.layer.as_ref()) {
+                    if let Err(i) = self.layers.binary_search(layer) {
+                        self.layers.insert(i, layer.clone());
+                    }
+                }
+                if self.tracks.iter().all(|t| t.frames.is_empty()) && !self.fixed_view {
+                    self.frame_rect = viewable_rect(frame.rect);
+                }
+                self.tracks[idx].frames.push(frame);
+                self.color_by.invalidate();
+                if idx == self.track_idx {
+                    // following waits until the frame asked for arrived
+                    if self.pending_frame == Some(n) {
+                        self.pending_frame = None;
+                        self.frame_idx = n;
+                    }
+                    else if self.live.follow && self.pending_frame.is_none() {
+                        self.frame_idx = n;
+                    }
+                }
+            }
+            Packet::Sample { series, step, value } => {
+                match self.series.iter_mut().find(|s| s.name == series) {
+                    Some(s) => s.samples.push((step, value)),
+                    None => self.series.push(Series::new(series).add_sample(step, value)),
+                }
+            }
+            Packet::Series(series) => {
+                match self.series.iter_mut().find(|s| s.name == series.name) {
+                    Some(s) => s.samples.extend(series.samples),
+                    None => self.series.push(series),
+                }
+            }
+        }
+    }
+
+    /// Opens the file at `path`, reporting a failure in `msg`. Session files are restored.
+    fn open(&mut self, path: PathBuf) -> bool {
+        if path.extension().is_some_and(|e| e == session::EXTENSION) {
+            return match std::fs::read(&path) {
+                Ok(v) => {
+                    self.open_session(Some(&path), &v);
+                    true
+                }
+                Err(e) => {
+                    self.msg = format!("{}: {:?}", path.display(), e);
+                    false
+                }
+            };
+        }
+        match PaintData::from_file(&path) {
+            Ok(data) => {
+                self.load(data);
+                session::add_recent(&mut self.recent, path.clone());
+                self.watch.set_path(Some(path));
+                true
+            }
+            Err(e) => {
+                self.msg = format!("{}: {:?}", path.display(), e);
+                false
+            }
+        }
+    }
+
+    fn load_bytes(&mut self, path: Option<PathBuf>, v: Vec<u8>) {
+        match PaintData::from_u8s(v) {
+            Ok(data) => {
+                self.load(data);
+                if let Some(ref path) = path {
+                    session::add_recent(&mut self.recent, path.clone());
+                }
+                self.watch.set_path(path);
+            }
+            Err(e) => {
+                self.msg = format!("{:?}", e);
+            }
+        }
+    }
+
+    /// Loads the first file dropped onto the window. The web gives the bytes, native gives a path.
+    fn handle_dropped_files(&mut self, ctx: &Context) {
+        let hovering = ctx.input(|i| !i.raw.hovered_files.is_empty());
+        if hovering {
+            let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("drop_files")));
+            let rect = ctx.screen_rect();
+            painter.rect_filled(rect, 0.0, Color32::from_black_alpha(160));
+            painter.text(rect.center(), Align2::CENTER_CENTER, "drop to open", FontId::proportional(24.0), Color32::WHITE);
+        }
+        let Some(file) = ctx.input_mut(|i| std::mem::take(&mut i.raw.dropped_files)).into_iter().next() else { return };
+        if let Some(bytes) = file.bytes {
+            if file.name.ends_with(&format!(".{}", session::EXTENSION)) {
+                self.open_session(None, &bytes);
+            }
+            else {
+                self.load_bytes(None, bytes.to_vec());
+            }
+        }
+        else if let Some(path) = file.path {
+            self.open(path);
+        }
+    }
+
+    /// Asks where to save the current session, with the data path relative to it.
+    fn save_session_file(&mut self) {
+        let mut session = self.session();
+        session.recent.clear();
+        let task = rfd::AsyncFileDialog::new()
+            .add_filter("session", &[session::EXTENSION])
+            .set_file_name(format!("view.{}", session::EXTENSION))
+            .save_file();
+        execute(async move {
+            let Some(file) = task.await else { return };
+            #[cfg(not(target_arch = "wasm32"))]
+            let session = match file.path().parent() {
+                Some(dir) => session.relative_to(dir),
+                None => session,
+            };
+            let res = match session.to_ron() {
+                Ok(s) => file.write(s.as_bytes()).await.map_err(anyhow::Error::from),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                eprintln!("saving the session failed: {:?}", e);
+            }
+        });
+    }
+
+    /// The frame of track `track_idx` on screen. Side by side, shorter tracks keep showing their last frame.
+    fn shown_frame(&self, track_idx: usize) -> usize {
+        self.tracks.get(track_idx).map_or(0, |t| self.frame_idx.min(t.frames.len().saturating_sub(1)))
+    }
+
+    /// The world point under the pointer, snapped while measuring. Uses the canvases of the
+    /// last update, as the status bar is laid out before the canvas.
+    fn pointer_world(&self, ctx: &Context) -> Option<Pos2> {
+        let p = ctx.pointer_hover_pos()?;
+        let &(track_idx, _, to_screen) = self.canvases.iter().find(|c| c.1.contains(p))?;
+        let frame = self.tracks.get(track_idx).and_then(|t| t.frames.get(self.shown_frame(track_idx)));
+        match frame {
+            Some(frame) if self.measure.active => {
+                let shown = frame.elems.iter().filter(|e| self.filter.visibility(e) == filter::Visibility::Shown);
+                Some(self.measure.target(p, &to_screen, shown))
+            }
+            _ => Some(to_screen.inverse() * p),
+        }
+    }
+
+    fn show_canvas(&mut self, ui: &mut Ui, track_idx: usize) {
+        let default_frame = PaintFrame::default();
+        let frame_idx = self.shown_frame(track_idx);
+        if let Some(frame) = self.tracks.get_mut(track_idx).and_then(|t| t.frames.get_mut(frame_idx)) {
+            ui.fonts(|f| frame.measure_texts(f));
+        }
+        let frame = match self.tracks.get(track_idx) {
+            Some(track) if !track.frames.is_empty() => &track.frames[frame_idx],
+            _ => &default_frame,
+        };
+        let ui_size = ui.available_size_before_wrap();
+        let fr_size = viewable_rect(frame.rect).size();
+        let max_mul = {
+            let xp = ui_size.x / fr_size.x;
+            let yp = ui_size.y / fr_size.y;
+            if xp > yp { yp } else { xp }
+        };
+        let (mut response, painter) =
+            ui.allocate_painter(ui_size, Sense::click_and_drag());
+            //ui.allocate_painter(fr_size * max_mul, Sense::drag());
+
+        let to_screen = emath::RectTransform::from_to(
+            self.frame_rect,
+            Rect::from_center_size(response.rect.center(), fr_size * max_mul),
+            //Rect::from_min_max(Pos2::ZERO, (fr_size * max_mul).to_pos2()),
+            //response.rect,
+            );
+        let from_screen = to_screen.inverse();
+
+        for elem in frame.elems.iter() {
+            let visibility = self.filter.visibility(elem);
+            if visibility == filter::Visibility::Hidden {
+                continue;
+            }
+            let dimmed = visibility == filter::Visibility::Dimmed;
+            match &elem.shape {
+                ElementKind::Shape(shape) => {
+                    let shape = match self.color_by.color(elem) {
+                        Some(color) => coloring::recolor(shape.clone(), color),
+                        None => shape.clone(),
+                    };
+                    let shape = if dimmed { filter::dim(shape) } else { shape };
+                    painter.add(transform::shape_transform(shape, &to_screen).unwrap());
+                }
+                ElementKind::Text(text) => {
+                    let color = self.color_by.color(elem).unwrap_or(text.color);
+                    let color = if dimmed { filter::dim_color(color) } else { color };
+                    let galley = painter.layout_no_wrap(text.text.clone(), FontId::proportional(text.size * (to_screen.scale().x * to_screen.scale().y).sqrt()), color);
+                    let rect = galley.rect;
+                    //painter.rect_filled(Rect::from_min_max(rect.min + (pointer_pos - rect.max), pointer_pos), 0.0, Color32::WHITE);
+                    painter.galley(to_screen * text.pos - rect.size() / 2.0, galley, Color32::PLACEHOLDER);
+                }
+                ElementKind::Legend(_) => {}
+            }
+        }
+
+        if let Some((t, f, e)) = self.selected {
+            if let Some(elem) = frame.elems.get(e).filter(|_| t == track_idx && f == frame_idx) {
+                outline(ui, &painter, &to_screen, elem, Stroke::new(2.0, HIGHLIGHT));
+            }
+        }
+
+        let mut legends = legend::Legends::new(&painter, response.rect);
+        self.color_by.paint_colorbar(&mut legends);
+        for elem in frame.elems.iter() {
+            if let ElementKind::Legend(ref l) = elem.shape {
+                legends.legend(l);
+            }
+        }
+
+        //eprintln!("{:?}", shapes);
+        //painter.rect_filled(painter.clip_rect(), 0.0, Color32::WHITE);
+        //painter.extend(shapes);
+
+        let target = response.hover_pos().map(|p| {
+            if self.measure.active {
+                let shown = frame.elems.iter().filter(|e| self.filter.visibility(e) == filter::Visibility::Shown);
+                self.measure.target(p, &to_screen, shown)
+            }
+            else {
+                from_screen * p
+            }
+        });
+        self.measure.paint(&painter, &to_screen, target);
+        self.canvases.push((track_idx, response.rect, to_screen));
+
+        if let Some(pointer_pos) = response.hover_pos() {
+            ui.ctx().input(|i| {
+                let zd = i.zoom_delta();
+                let (x, y) = 
+                    if i.key_down(Key::Z) { (zd, 1.0) }
+                    else if i.key_down(Key::X) { (1.0, zd) }
+                    else { (zd, zd) };
+                {
+                    let p = from_screen * pointer_pos;
+                    self.frame_rect = Rect::from_min_max(
+                        p + ((self.frame_rect.min - p) / x ),
+                        p + ((self.frame_rect.max - p) / y ),
+                    );
+                }
+            });
+            // topmost first
+            let hits = frame.elems.iter().enumerate().rev()
+                .filter(|(_, e)| self.filter.visibility(e) == filter::Visibility::Shown)
+                .filter(|(_, e)| e.hover.as_ref().is_some_and(|h| h.check(pointer_pos, &to_screen)))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            let clicked = if self.stack.enabled {
+                self.stack.update((track_idx, frame_idx), hits.clone());
+                self.stack.handle_input(ui.ctx());
+                self.stack.active()
+            }
+            else {
+                hits.first().copied()
+            };
+            if response.clicked() && self.measure.active {
+                self.measure.click(target.unwrap_or(from_screen * pointer_pos));
+            }
+            else if response.clicked() {
+                self.selected = clicked.map(|i| (track_idx, frame_idx, i));
+                if self.selected.is_some() {
+                    self.inspector.open = true;
+                }
+            }
+            if self.stack.enabled {
+                if let Some(i) = self.stack.active() {
+                    outline(ui, &painter, &to_screen, &frame.elems[i], Stroke::new(1.0, HIGHLIGHT));
+                    response = response.on_hover_ui_at_pointer(|ui| self.stack.tooltip(ui, frame));
+                }
+            }
+            else if let Some(h) = hits.iter().filter_map(|&i| frame.elems[i].hover.as_ref()).find(|h| !h.msg.is_empty()) {
+                response = response.on_hover_text_at_pointer(h.msg.clone());
+            }
+        }
+
+        if let Some(after) = response.interact_pointer_pos() {
+            if let Some(before) = self.drag_pos.take() {
+                self.frame_rect.set_center(self.frame_rect.center() - (from_screen * after - from_screen * before));
+            }
+            self.drag_pos = Some(after);
+        }
+        else if !ui.ctx().input(|i| i.pointer.any_down()) {
+            // only forget the drag on release, so the other canvases side by side do not drop it
+            self.drag_pos = None;
         }
     }
 }
 
 impl eframe::App for EguiSample {
-    fn save(&mut self, _storage: &mut dyn eframe::Storage) {}       
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.session());
+    }
+
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
         ctx.style_mut(|style| style.interaction.tooltip_delay = 0.0);
+        self.handle_dropped_files(ctx);
+        self.filter.prepare();
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::Space)) {
+            self.playback.toggle();
+        }
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::M)) {
+            self.measure.active = !self.measure.active;
+            self.measure.clear();
+        }
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.measure.clear();
+        }
+        let markers = self.markers();
+        if !ctx.wants_keyboard_input() {
+            // consumed here, so the plain arrow keys below do not step as well
+            for (key, forward) in [(Key::ArrowRight, true), (Key::ArrowLeft, false)] {
+                if ctx.input_mut(|i| i.consume_key(Modifiers::SHIFT, key)) {
+                    if let Some(i) = timeline::step(&markers, self.frame_idx, forward) {
+                        self.frame_idx = i;
+                    }
+                }
+            }
+        }
+        let count = self.frame_count();
+        let track = self.tracks.get(self.track_idx);
+        self.playback.tick(ctx, &mut self.frame_idx, count, |i| track.and_then(|t| t.frames.get(i)).is_some_and(|f| f.keyframe));
         SidePanel::right("here").show(ctx, |ui| {
+            if self.tracks.len() > 1 {
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_salt("track")
+                        .selected_text(self.tracks.get(self.track_idx).map_or("", |t| t.label()))
+                        .show_ui(ui, |ui| {
+                            for (i, track) in self.tracks.iter().enumerate() {
+                                ui.selectable_value(&mut self.track_idx, i, track.label());
+                            }
+                        });
+                    ui.checkbox(&mut self.side_by_side, "side by side");
+                });
+            }
+
             let mut idx_i32 = self.frame_idx as i32;
-            ui.add(Slider::new(
-                    &mut idx_i32,
-                    0i32..=((std::cmp::max(self.frames.len(), 1) - 1) as i32)
-                
-                ).smart_aim(false)
-                );
+            let last = std::cmp::max(self.frame_count(), 1) - 1;
+            ui.horizontal(|ui| {
+                let slider = ui.add(Slider::new(&mut idx_i32, 0i32..=last as i32).smart_aim(false).show_value(false));
+                timeline::ticks(ui, &slider, last, &markers);
+                ui.add(DragValue::new(&mut idx_i32).range(0i32..=last as i32));
+            });
             self.frame_idx = idx_i32 as usize;
+            if !markers.is_empty() {
+                ui.horizontal(|ui| {
+                    let prev = timeline::step(&markers, self.frame_idx, false);
+                    let next = timeline::step(&markers, self.frame_idx, true);
+                    if ui.add_enabled(prev.is_some(), Button::new("previous marker")).on_hover_text("shift + left").clicked() {
+                        self.frame_idx = prev.unwrap_or(self.frame_idx);
+                    }
+                    if ui.add_enabled(next.is_some(), Button::new("next marker")).on_hover_text("shift + right").clicked() {
+                        self.frame_idx = next.unwrap_or(self.frame_idx);
+                    }
+                });
+            }
+
+            self.playback.ui(ui, count);
+
+            ui.horizontal(|ui| {
+                if ui.button("reset view").clicked() && self.frame_idx < self.frames().len() {
+                    self.frame_rect = viewable_rect(self.frames()[self.frame_idx].rect);
+                }
+                if ui.button("fit to content").clicked() && self.frame_idx < self.frames().len() {
+                    let frame = &self.frames()[self.frame_idx];
+                    let content = ui.fonts(|f| frame.content_rect(f));
+                    if content.is_positive() {
+                        self.frame_rect = fit_aspect(content.expand(content.size().max_elem() * 0.02), viewable_rect(frame.rect).size());
+                    }
+                }
+                ui.toggle_value(&mut self.inspector.open, "inspector");
+            });
+            ui.checkbox(&mut self.stack.enabled, "list all elements under the cursor");
+            ui.horizontal(|ui| {
+                if ui.toggle_value(&mut self.measure.active, "measure").on_hover_text("M; click two points, escape to clear").changed() {
+                    self.measure.clear();
+                }
+                if self.measure.active {
+                    ui.checkbox(&mut self.measure.snap, "snap to vertices");
+                }
+            });
 
-            if ui.button("reset view").clicked() && self.frame_idx < self.frames.len() {
-                self.frame_rect = self.frames[self.frame_idx].rect;
+            if !self.attrs.is_empty() {
+                CollapsingHeader::new("color by attribute").show(ui, |ui| {
+                    self.color_by.ui(ui, &self.attrs, &self.tracks);
+                });
             }
 
             if ui.button("select file").clicked() {
@@ -66,118 +736,170 @@ impl eframe::App for EguiSample {
                     let file = task.await;
                     eprintln!("file picked {:?}", file);
                     if let Some(file) = file {
-                        let _ = dbg!(sender.send(file.read().await));
+                        #[cfg(not(target_arch = "wasm32"))]
+                        let path = Some(file.path().to_owned());
+                        #[cfg(target_arch = "wasm32")]
+                        let path = None;
+                        let _ = sender.send((path, file.read().await));
                         ctx.request_repaint();
                     }
                 });
             }
-            if let Ok(v) = self.selected_file.1.try_recv() {
-                match dbg!(PaintFrame::from_u8s(v)) {
-                    Ok(frames) => {
-                        self.frames = frames;
-                        self.frame_idx = 0;
-                        if 0 < self.frames.len() {
-                            self.frame_rect = self.frames[0].rect.clone();
+            let mut reopen = None;
+            ui.add_enabled_ui(!self.recent.is_empty(), |ui| {
+                ui.menu_button("recent", |ui| {
+                    for path in &self.recent {
+                        let name = path.file_name().map_or_else(|| path.to_string_lossy(), |n| n.to_string_lossy());
+                        if ui.button(name).on_hover_text(path.to_string_lossy()).clicked() {
+                            reopen = Some(path.clone());
+                            ui.close_menu();
                         }
                     }
-                    Err(e) => {
-                        self.msg = format!("{:?}", e);
-                    }
+                });
+            });
+            if let Some(path) = reopen {
+                self.open(path);
+            }
+            if let Ok((path, v)) = self.selected_file.1.try_recv() {
+                self.load_bytes(path, v);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("save session").clicked() {
+                    self.save_session_file();
                 }
+                if ui.button("open session").clicked() {
+                    let sender = self.selected_session.0.clone();
+                    let task = rfd::AsyncFileDialog::new().add_filter("session", &[session::EXTENSION]).pick_file();
+                    let ctx = ui.ctx().clone();
+                    execute(async move {
+                        if let Some(file) = task.await {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            let path = Some(file.path().to_owned());
+                            #[cfg(target_arch = "wasm32")]
+                            let path = None;
+                            let _ = sender.send((path, file.read().await));
+                            ctx.request_repaint();
+                        }
+                    });
+                }
+            });
+            if let Ok((path, v)) = self.selected_session.1.try_recv() {
+                self.open_session(path.as_deref(), &v);
             }
+            CollapsingHeader::new("filter").show(ui, |ui| {
+                self.filter.ui(ui, &self.layers, &self.attrs);
+            });
 
-            ui.label(format!("{}", self.msg));
-        });
+            let mut hit = None;
+            CollapsingHeader::new("search").show(ui, |ui| {
+                hit = self.search.ui(ui, &self.tracks).map(|h| (h.track, h.frame, h.elem));
+            });
+            if let Some(hit) = hit {
+                self.reveal(ui.ctx(), hit);
+            }
+            CollapsingHeader::new("bookmarks").show(ui, |ui| {
+                let (track, frame, view) = (self.tracks.get(self.track_idx).and_then(|t| t.name.clone()), self.frame_idx, self.frame_rect);
+                let here = |name, note| bookmarks::Bookmark { name, track, frame, view, note };
+                if let Some(b) = self.bookmarks.ui(ui, here) {
+                    self.go_to(b.track, b.frame, Some(b.view));
+                }
+            });
+            CollapsingHeader::new("live").show(ui, |ui| self.live.ui(ui));
+            for packet in self.live.poll() {
+                self.append(packet);
+            }
 
-        CentralPanel::default().frame(Frame::none().fill(Color32::WHITE)).show(ctx, |ui| {
-            let default_frame = PaintFrame::default();
-            let frame = if self.frame_idx < self.frames.len() { &self.frames[self.frame_idx] } else { &default_frame };
-            let ui_size = ui.available_size_before_wrap();
-            let fr_size = frame.rect.size();
-            let max_mul = {
-                let xp = ui_size.x / fr_size.x;
-                let yp = ui_size.y / fr_size.y;
-                if xp > yp { yp } else { xp }
-            };
-            let (mut response, painter) =
-                ui.allocate_painter(ui_size, Sense::drag());
-                //ui.allocate_painter(fr_size * max_mul, Sense::drag());
-
-            let to_screen = emath::RectTransform::from_to(
-                self.frame_rect,
-                Rect::from_center_size(response.rect.center(), fr_size * max_mul),
-                //Rect::from_min_max(Pos2::ZERO, (fr_size * max_mul).to_pos2()),
-                //response.rect,
-                );
-            let from_screen = to_screen.inverse();
-
-            for elem in frame.elems.iter() {
-                match &elem.shape {
-                    ElementKind::Shape(shape) => {
-                        painter.add(transform::shape_transform(shape.clone(), &to_screen).unwrap());
-                    }
-                    ElementKind::Text(text) => {
-                        let galley = painter.layout_no_wrap(text.text.clone(), FontId::proportional(text.size * (to_screen.scale().x * to_screen.scale().y).sqrt()), text.color);
-                        let rect = galley.rect.clone();
-                        //painter.rect_filled(Rect::from_min_max(rect.min + (pointer_pos - rect.max), pointer_pos), 0.0, Color32::WHITE);
-                        painter.galley(to_screen * text.pos - rect.size() / 2.0, galley, Color32::PLACEHOLDER);
+            if let Some(name) = self.watch.path().and_then(|p| p.file_name()) {
+                let label = format!("watch {}", name.to_string_lossy());
+                ui.checkbox(&mut self.watch.enabled, label);
+            }
+            if let Some(v) = self.watch.poll(ui.ctx()) {
+                match PaintData::from_u8s(v) {
+                    Ok(data) => {
+                        self.reload(data);
+                        self.watch.loaded();
+                        self.msg = String::new();
                     }
+                    Err(e) => self.msg = format!("reload failed, trying again: {:?}", e),
                 }
             }
 
-            //eprintln!("{:?}", shapes);
-            //painter.rect_filled(painter.clip_rect(), 0.0, Color32::WHITE);
-            //painter.extend(shapes);
-
-            self.msg = String::new();
-            if let Some(pointer_pos) = response.hover_pos() {
-                ctx.input(|i| {
-                    let zd = i.zoom_delta();
-                    let (x, y) = 
-                        if i.key_down(Key::Z) { (zd, 1.0) }
-                        else if i.key_down(Key::X) { (1.0, zd) }
-                        else { (zd, zd) };
-                    {
-                        let p = from_screen * pointer_pos;
-                        self.frame_rect = Rect::from_min_max(
-                            p + ((self.frame_rect.min - p) / x ),
-                            p + ((self.frame_rect.max - p) / y ),
-                        );
+            ui.label(&self.msg);
+
+            if !self.diagnostics.is_empty() {
+                CollapsingHeader::new(format!("warnings ({})", self.diagnostics.len())).show(ui, |ui| {
+                    let row_height = ui.text_style_height(&TextStyle::Body);
+                    let mut clicked = None;
+                    ScrollArea::vertical().max_height(240.0).show_rows(ui, row_height, self.diagnostics.len(), |ui, range| {
+                        for d in &self.diagnostics[range] {
+                            if ui.link(d.to_string()).clicked() {
+                                clicked = Some(d.clone());
+                            }
+                        }
+                    });
+                    if let Some(d) = clicked {
+                        self.show_diagnostic(ui.ctx(), d);
                     }
                 });
-                for h in frame.elems.iter().rev().filter_map(|e| e.hover.as_ref()) {
-                    if h.check(pointer_pos, &to_screen) {
-                        response = response.on_hover_text_at_pointer(&h.msg.clone());
-                        /*
-                        let gallary = painter.layout_no_wrap(self.msg.clone(), FontId::proportional(8.0), Color32::BLACK);
-                        let rect = gallary.rect.clone();
-                        painter.rect_filled(Rect::from_min_max(rect.min + (pointer_pos - rect.max), pointer_pos), 0.0, Color32::WHITE);
-                        painter.galley((pointer_pos - rect.max).to_pos2(), gallary);
-                        response.mark_changed();
-                        */
-                        break;
-                    }
-                }
             }
+        });
 
-            if let Some(after) = response.interact_pointer_pos() {
-                if let Some(before) = self.drag_pos.take() {
-                    self.frame_rect.set_center(self.frame_rect.center() - (from_screen * after - from_screen * before));
+        if self.inspector.open {
+            let frame_idx = self.shown_frame(self.track_idx);
+            let action = SidePanel::left("inspector").resizable(true).default_width(280.0).show(ctx, |ui| {
+                self.inspector.ui(ui, &self.tracks, self.track_idx, frame_idx, self.selected)
+            }).inner;
+            match action {
+                Some(inspector::Action::Select(r)) => self.selected = Some(r),
+                Some(inspector::Action::Reveal(r)) => self.reveal(ctx, r),
+                None => {}
+            }
+        }
+
+        let pointer = self.pointer_world(ctx);
+        TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                match pointer {
+                    Some(p) => ui.label(format!("x {:.3}  y {:.3}", p.x, p.y)),
+                    None => ui.label(""),
+                };
+                if let Some((a, b)) = self.measure.segment(pointer) {
+                    ui.separator();
+                    ui.label(measure::describe(a, b));
                 }
-                self.drag_pos = Some(after);
+            });
+        });
+
+        if !self.series.is_empty() {
+            TopBottomPanel::bottom("chart").resizable(true).default_height(160.0).show(ctx, |ui| {
+                let cursor = self.frames().get(self.frame_idx).map_or(0, |f| f.step);
+                if let Some(step) = self.chart.show(ui, &self.series, cursor) {
+                    self.frame_idx = self.frames().partition_point(|f| f.step <= step).saturating_sub(1);
+                }
+            });
+        }
+
+        self.canvases.clear();
+        CentralPanel::default().frame(Frame::none().fill(Color32::WHITE)).show(ctx, |ui| {
+            if self.side_by_side && self.tracks.len() > 1 {
+                ui.columns(self.tracks.len(), |cols| {
+                    for (i, ui) in cols.iter_mut().enumerate() {
+                        ui.label(self.tracks[i].label());
+                        self.show_canvas(ui, i);
+                    }
+                });
             }
             else {
-                self.drag_pos = None;
+                self.show_canvas(ui, self.track_idx);
             }
-            self.msg = format!("{:?}", self.drag_pos);
         });
-        if ctx.input(|i| i.key_pressed(Key::ArrowRight)) {
-            if self.frame_idx + 1 < self.frames.len() {
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::ArrowRight)) {
+            if self.frame_idx + 1 < self.frame_count() {
                 self.frame_idx += 1;
             }
             ctx.request_repaint();
         }
-        if ctx.input(|i| i.key_pressed(Key::ArrowLeft)) {
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::ArrowLeft)) {
             if self.frame_idx > 0 {
                 self.frame_idx -= 1;
             }
@@ -186,6 +908,48 @@ impl eframe::App for EguiSample {
     }
 }
 
+/// The outline of the selected element.
+const HIGHLIGHT: Color32 = Color32::from_rgb(255, 0, 200);
+
+/// Draws a box around `elem`.
+fn outline(ui: &Ui, painter: &Painter, to_screen: &emath::RectTransform, elem: &parser::FrameElement, stroke: Stroke) {
+    let bounds = ui.fonts(|f| elem.bounds(f));
+    if bounds.is_finite() {
+        let rect = Rect::from_two_pos(*to_screen * bounds.min, *to_screen * bounds.max).expand(4.0);
+        painter.rect_stroke(rect, 2.0, stroke);
+    }
+}
+
+/// Falls back to a finite rect with a non-zero area, so a broken frame rect
+/// does not turn the scale in `update` into NaN.
+fn viewable_rect(rect: Rect) -> Rect {
+    if !rect.is_finite() {
+        return Rect::from_min_max(pos2(0.0, 0.0), pos2(100.0, 100.0));
+    }
+    let mut rect = rect;
+    if rect.width() <= 0.0 {
+        rect = rect.expand2(vec2(0.5, 0.0));
+    }
+    if rect.height() <= 0.0 {
+        rect = rect.expand2(vec2(0.0, 0.5));
+    }
+    rect
+}
+
+/// Grows `rect` around its center until it has the aspect ratio of `size`,
+/// which is the aspect ratio the canvas maps `frame_rect` to.
+fn fit_aspect(rect: Rect, size: Vec2) -> Rect {
+    let aspect = size.x / size.y;
+    let mut fit = rect.size();
+    if fit.x / fit.y > aspect {
+        fit.y = fit.x / aspect;
+    }
+    else {
+        fit.x = fit.y * aspect;
+    }
+    Rect::from_center_size(rect.center(), fit)
+}
+
 use std::future::Future;
 #[cfg(not(target_arch = "wasm32"))]
 fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
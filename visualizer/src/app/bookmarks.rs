@@ -0,0 +1,55 @@
+use eframe::egui::*;
+use serde::{ Serialize, Deserialize };
+
+/// A named place in the data: a frame of a track, seen through a view rect.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bookmark {
+    pub name: String,
+    pub track: Option<String>,
+    pub frame: usize,
+    pub view: Rect,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// The user's bookmarks, with the name and note of the next one being typed.
+#[derive(Default)]
+pub struct Bookmarks {
+    pub list: Vec<Bookmark>,
+    name: String,
+    note: String,
+}
+
+impl Bookmarks {
+    /// Lists the bookmarks and returns the one clicked. `here` bookmarks the current place
+    /// under the given name and note.
+    pub fn ui(&mut self, ui: &mut Ui, here: impl FnOnce(String, String) -> Bookmark) -> Option<Bookmark> {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.name).hint_text("name").desired_width(100.0));
+            if ui.button("add").clicked() {
+                let name = if self.name.trim().is_empty() { format!("bookmark {}", self.list.len() + 1) } else { self.name.trim().to_owned() };
+                self.list.push(here(name, std::mem::take(&mut self.note)));
+                self.name.clear();
+            }
+        });
+        ui.add(TextEdit::multiline(&mut self.note).hint_text("note").desired_rows(1));
+        let mut jump = None;
+        let mut remove = None;
+        for (i, b) in self.list.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").on_hover_text("delete").clicked() {
+                    remove = Some(i);
+                }
+                let link = ui.link(format!("{} (frame {})", b.name, b.frame));
+                let link = if b.note.is_empty() { link } else { link.on_hover_text(&b.note) };
+                if link.clicked() {
+                    jump = Some(b.clone());
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.list.remove(i);
+        }
+        jump
+    }
+}
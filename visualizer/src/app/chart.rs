@@ -0,0 +1,105 @@
+use eframe::egui::*;
+use visualizer_shapes::Series;
+
+fn series_color(idx: usize) -> Color32 {
+    let c = visualizer_shapes::Color::tag(idx);
+    Color32::from_rgb(c.r, c.g, c.b)
+}
+
+/// The value of the last sample at or before `step`.
+fn value_at(series: &Series, step: u64) -> Option<f64> {
+    let idx = series.samples.partition_point(|s| s.0 <= step);
+    if idx == 0 { None } else { Some(series.samples[idx - 1].1) }
+}
+
+/// A timeline of the time series. Every series is scaled to its own range, since
+/// scores and temperatures rarely share units.
+#[derive(Default)]
+pub struct Chart {
+    visible: Vec<bool>,
+}
+
+impl Chart {
+    /// Shows the series with a cursor at `cursor`, and returns the step under the pointer
+    /// while the chart is clicked or dragged.
+    pub fn show(&mut self, ui: &mut Ui, series: &[Series], cursor: u64) -> Option<u64> {
+        self.visible.resize(series.len(), true);
+        ui.horizontal_wrapped(|ui| {
+            for (i, s) in series.iter().enumerate() {
+                let text = match value_at(s, cursor) {
+                    Some(v) => format!("{} = {}", s.name, v),
+                    None => s.name.clone(),
+                };
+                ui.checkbox(&mut self.visible[i], RichText::new(text).color(series_color(i)));
+            }
+        });
+
+        let visible = series.iter().zip(self.visible.iter()).enumerate()
+            .filter(|(_, (_, &v))| v)
+            .map(|(i, (s, _))| (i, s))
+            .collect::<Vec<_>>();
+        let (min_step, max_step) = visible.iter()
+            .flat_map(|(_, s)| s.samples.first().into_iter().chain(s.samples.last()))
+            .fold((u64::MAX, 0), |(lo, hi), &(step, _)| (lo.min(step), hi.max(step)));
+        let (response, painter) = ui.allocate_painter(ui.available_size_before_wrap(), Sense::click_and_drag());
+        let rect = response.rect.shrink(4.0);
+        painter.rect_filled(response.rect, 0.0, ui.visuals().extreme_bg_color);
+        if min_step > max_step {
+            return None;
+        }
+        let span = (max_step - min_step).max(1) as f32;
+        let to_x = |step: u64| rect.left() + (step - min_step.min(step)) as f32 / span * rect.width();
+        let to_step = |x: f32| min_step + (((x - rect.left()) / rect.width()).clamp(0.0, 1.0) * span).round() as u64;
+
+        for &(i, s) in visible.iter() {
+            let (lo, hi) = s.samples.iter()
+                .map(|s| s.1)
+                .filter(|v| v.is_finite())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+            if lo > hi {
+                continue;
+            }
+            let to_y = |v: f64| rect.bottom() - if hi > lo { ((v - lo) / (hi - lo)) as f32 * rect.height() } else { rect.height() / 2.0 };
+            // one min/max pair per pixel column keeps millions of samples cheap to draw
+            let mut points = vec![];
+            let mut column: Option<(i64, f64, f64)> = None;
+            for &(step, v) in s.samples.iter().filter(|s| s.1.is_finite()) {
+                let x = to_x(step).floor() as i64;
+                column = match column {
+                    Some((cx, cmin, cmax)) if cx == x => Some((cx, cmin.min(v), cmax.max(v))),
+                    Some((cx, cmin, cmax)) => {
+                        points.push(pos2(cx as f32, to_y(cmin)));
+                        points.push(pos2(cx as f32, to_y(cmax)));
+                        Some((x, v, v))
+                    }
+                    None => Some((x, v, v)),
+                };
+            }
+            if let Some((cx, cmin, cmax)) = column {
+                points.push(pos2(cx as f32, to_y(cmin)));
+                points.push(pos2(cx as f32, to_y(cmax)));
+            }
+            painter.add(Shape::line(points, Stroke::new(1.5, series_color(i))));
+        }
+
+        let cursor_x = to_x(cursor.clamp(min_step, max_step));
+        painter.vline(cursor_x, response.rect.y_range(), Stroke::new(1.5, Color32::RED));
+
+        if let Some(p) = response.hover_pos() {
+            let step = to_step(p.x);
+            painter.vline(p.x, response.rect.y_range(), Stroke::new(1.0, Color32::GRAY));
+            let text = std::iter::once(format!("step {}", step))
+                .chain(visible.iter().filter_map(|(_, s)| value_at(s, step).map(|v| format!("{} = {}", s.name, v))))
+                .collect::<Vec<_>>()
+                .join("\n");
+            response.clone().on_hover_text_at_pointer(text);
+        }
+
+        if response.is_pointer_button_down_on() {
+            response.interact_pointer_pos().map(|p| to_step(p.x))
+        }
+        else {
+            None
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use eframe::egui::*;
+use eframe::epaint::ColorMode;
+use serde::{ Serialize, Deserialize };
+use visualizer_shapes::{ Colormap, Corner };
+
+use super::legend;
+use super::parser::{ FrameElement, PaintTrack };
+
+/// Colors elements by one of their numeric attributes, overriding the producer's colors.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorBy {
+    pub attr: Option<String>,
+    pub colormap: Colormap,
+    pub auto_range: bool,
+    pub range: (f32, f32),
+    pub log: bool,
+    /// The attribute and scale `range` was computed for.
+    #[serde(skip)]
+    computed: Option<(String, bool)>,
+}
+
+impl Default for ColorBy {
+    fn default() -> Self {
+        Self {
+            attr: None,
+            colormap: Colormap::default(),
+            auto_range: true,
+            range: (0.0, 1.0),
+            log: false,
+            computed: None,
+        }
+    }
+}
+
+/// The range of `name` over every frame, skipping values a log scale can not show.
+fn value_range(tracks: &[PaintTrack], name: &str, log: bool) -> Option<(f32, f32)> {
+    let (lo, hi) = tracks.iter()
+        .flat_map(|t| t.frames.iter())
+        .flat_map(|f| f.elems.iter())
+        .filter_map(|e| e.attr(name))
+        .filter(|v| v.is_finite() && (!log || *v > 0.0))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if lo <= hi { Some((lo, hi)) } else { None }
+}
+
+fn format_value(v: f32) -> String {
+    if v != 0.0 && (v.abs() >= 1e5 || v.abs() < 1e-3) { format!("{:.3e}", v) } else { format!("{:.4}", v) }
+}
+
+impl ColorBy {
+    /// Recomputes the automatic range on the next `ui`, e.g. after loading a file.
+    pub fn invalidate(&mut self) {
+        self.computed = None;
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, names: &[String], tracks: &[PaintTrack]) {
+        ComboBox::from_label("attribute")
+            .selected_text(self.attr.as_deref().unwrap_or("none"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.attr, None, "none");
+                for name in names {
+                    ui.selectable_value(&mut self.attr, Some(name.clone()), name);
+                }
+            });
+        let Some(attr) = self.attr.clone() else { return };
+        ComboBox::from_label("colormap")
+            .selected_text(self.colormap.name())
+            .show_ui(ui, |ui| {
+                for map in Colormap::ALL {
+                    ui.selectable_value(&mut self.colormap, map, map.name());
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.log, "log scale");
+            ui.checkbox(&mut self.auto_range, "auto range");
+        });
+        let key = Some((attr.clone(), self.log));
+        if self.auto_range && self.computed != key {
+            if let Some(range) = value_range(tracks, &attr, self.log) {
+                self.range = range;
+            }
+            self.computed = key;
+        }
+        ui.add_enabled_ui(!self.auto_range, |ui| {
+            ui.horizontal(|ui| {
+                let speed = ((self.range.1 - self.range.0).abs() / 100.0).max(1e-3);
+                ui.add(DragValue::new(&mut self.range.0).speed(speed).prefix("min "));
+                ui.add(DragValue::new(&mut self.range.1).speed(speed).prefix("max "));
+            });
+        });
+    }
+
+    /// The colormap position of `v`, or `None` if it can not be placed on the scale.
+    fn position(&self, v: f32) -> Option<f32> {
+        let (lo, hi) = self.range;
+        let (v, lo, hi) = if self.log {
+            if v <= 0.0 || lo <= 0.0 || hi <= 0.0 {
+                return None;
+            }
+            (v.ln(), lo.ln(), hi.ln())
+        }
+        else {
+            (v, lo, hi)
+        };
+        if !v.is_finite() {
+            None
+        }
+        else if hi == lo {
+            Some(0.5)
+        }
+        else {
+            Some((v - lo) / (hi - lo))
+        }
+    }
+
+    /// The color of `elem`, if it is colored by attribute.
+    pub fn color(&self, elem: &FrameElement) -> Option<Color32> {
+        let v = elem.attr(self.attr.as_deref()?)?;
+        self.position(v).map(|t| legend::color32(self.colormap.eval(t)))
+    }
+
+    pub fn paint_colorbar(&self, legends: &mut legend::Legends<'_>) {
+        if let Some(attr) = self.attr.as_deref() {
+            let title = if self.log { format!("{} (log)", attr) } else { attr.to_owned() };
+            legends.colorbar(Corner::BottomRight, Some(&title), self.colormap, &format_value(self.range.0), &format_value(self.range.1));
+        }
+    }
+}
+
+/// Paints the fill of a filled shape with `color`, or its stroke otherwise.
+pub fn recolor(shape: Shape, color: Color32) -> Shape {
+    match shape {
+        Shape::Path(mut p) => {
+            if p.fill != Color32::TRANSPARENT {
+                p.fill = color;
+            }
+            else {
+                p.stroke.color = ColorMode::Solid(color);
+            }
+            Shape::Path(p)
+        }
+        Shape::Circle(mut c) => {
+            if c.fill != Color32::TRANSPARENT {
+                c.fill = color;
+            }
+            else {
+                c.stroke.color = color;
+            }
+            Shape::Circle(c)
+        }
+        shape => shape,
+    }
+}
@@ -0,0 +1,334 @@
+use eframe::egui::*;
+use eframe::epaint::ColorMode;
+use serde::{ Serialize, Deserialize };
+
+use super::parser::{ color::parse_color, ElementKind, FrameElement };
+
+/// The shapes an element can come from, as named by `FrameElement::kind`. Legends are never filtered.
+const KINDS: [&str; 4] = ["path", "circle", "text", "grid"];
+
+/// How far dimmed elements fade.
+const DIM: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    Hide,
+    Dim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Shown,
+    Dimmed,
+    Hidden,
+}
+
+/// The conditions in their typed form, rebuilt whenever the text fields change.
+#[derive(Clone)]
+struct Compiled {
+    msg: Option<regex::Regex>,
+    color: Option<[u8; 3]>,
+    ids: Vec<(u64, u64)>,
+}
+
+/// Parses "3, 5-10" into inclusive ranges.
+fn parse_ids(s: &str) -> anyhow::Result<Vec<(u64, u64)>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let (lo, hi) = s.split_once('-').unwrap_or((s, s));
+            let parse = |x: &str| x.trim().parse::<u64>().map_err(|_| anyhow::anyhow!("invalid id {:?}", x.trim()));
+            let (lo, hi) = (parse(lo)?, parse(hi)?);
+            if lo > hi {
+                anyhow::bail!("id range {:?} is reversed, write {}-{}", s, hi, lo);
+            }
+            Ok((lo, hi))
+        })
+        .collect()
+}
+
+fn rgb(c: Color32) -> [u8; 3] {
+    let [r, g, b, _] = c.to_srgba_unmultiplied();
+    [r, g, b]
+}
+
+/// Fades a color out for `Mode::Dim`.
+pub fn dim_color(c: Color32) -> Color32 {
+    c.gamma_multiply(DIM)
+}
+
+pub fn dim(shape: Shape) -> Shape {
+    match shape {
+        Shape::Path(mut p) => {
+            p.fill = dim_color(p.fill);
+            if let ColorMode::Solid(c) = p.stroke.color {
+                p.stroke.color = ColorMode::Solid(dim_color(c));
+            }
+            Shape::Path(p)
+        }
+        Shape::Circle(mut c) => {
+            c.fill = dim_color(c.fill);
+            c.stroke.color = dim_color(c.stroke.color);
+            Shape::Circle(c)
+        }
+        shape => shape,
+    }
+}
+
+/// Keeps the elements that meet every set condition, and hides or dims the others.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Filter {
+    pub enabled: bool,
+    pub mode: Mode,
+    /// Hides or dims the matching elements instead of the others.
+    pub invert: bool,
+    /// A regex the hover msg must match.
+    pub msg: String,
+    /// The shape kinds to keep, any if empty.
+    pub kinds: Vec<String>,
+    /// The layers to keep, any if empty.
+    pub layers: Vec<String>,
+    /// A color, in the syntax of the text color parser, the fill or the stroke must have.
+    pub color: String,
+    /// Ids and id ranges, e.g. "3, 5-10".
+    pub ids: String,
+    /// An attribute the element must have, within `attr_range`.
+    pub attr: Option<String>,
+    pub attr_range: (f32, f32),
+    #[serde(skip)]
+    compiled: Option<Result<Compiled, String>>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: Mode::Dim,
+            invert: false,
+            msg: String::new(),
+            kinds: vec![],
+            layers: vec![],
+            color: String::new(),
+            ids: String::new(),
+            attr: None,
+            attr_range: (0.0, 1.0),
+            compiled: None,
+        }
+    }
+}
+
+impl Filter {
+    fn compile(&self) -> Result<Compiled, String> {
+        let msg = match self.msg.trim() {
+            "" => None,
+            re => Some(regex::Regex::new(re).map_err(|e| e.to_string())?),
+        };
+        let color = match self.color.trim() {
+            "" => None,
+            s => match parse_color(s) {
+                Ok(("", c)) => Some(rgb(c)),
+                _ => return Err(format!("invalid color {:?}", s)),
+            },
+        };
+        let ids = parse_ids(&self.ids).map_err(|e| e.to_string())?;
+        Ok(Compiled { msg, color, ids })
+    }
+
+    /// Compiles the conditions if they changed, e.g. once per update.
+    pub fn prepare(&mut self) {
+        if self.compiled.is_none() {
+            self.compiled = Some(self.compile());
+        }
+    }
+
+    fn matches(&self, c: &Compiled, elem: &FrameElement) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|k| k == elem.kind) {
+            return false;
+        }
+        if !self.layers.is_empty() && !elem.layer.as_ref().is_some_and(|l| self.layers.contains(l)) {
+            return false;
+        }
+        if let Some(ref re) = c.msg {
+            if !elem.msg().is_some_and(|m| re.is_match(m)) {
+                return false;
+            }
+        }
+        if let Some(color) = c.color {
+            if !elem.colors().into_iter().flatten().any(|c| rgb(c) == color) {
+                return false;
+            }
+        }
+        if !c.ids.is_empty() && !elem.id.is_some_and(|id| c.ids.iter().any(|&(lo, hi)| lo <= id && id <= hi)) {
+            return false;
+        }
+        if let Some(ref name) = self.attr {
+            let (lo, hi) = self.attr_range;
+            if !elem.attr(name).is_some_and(|v| lo <= v && v <= hi) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// How to draw `elem`. Only shown elements can be hovered.
+    pub fn visibility(&self, elem: &FrameElement) -> Visibility {
+        let Some(Ok(ref c)) = self.compiled else { return Visibility::Shown };
+        if !self.enabled || matches!(elem.shape, ElementKind::Legend(_)) || self.matches(c, elem) != self.invert {
+            return Visibility::Shown;
+        }
+        match self.mode {
+            Mode::Hide => Visibility::Hidden,
+            Mode::Dim => Visibility::Dimmed,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, layers: &[String], attrs: &[String]) {
+        let before = (self.msg.clone(), self.color.clone(), self.ids.clone());
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.enabled, "filter");
+            ComboBox::from_id_salt("filter mode")
+                .selected_text(if self.mode == Mode::Hide { "hide" } else { "dim" })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.mode, Mode::Hide, "hide");
+                    ui.selectable_value(&mut self.mode, Mode::Dim, "dim");
+                });
+            ui.checkbox(&mut self.invert, "the matches");
+        }).response.on_hover_text("without \"the matches\", the elements that do not match are hidden or dimmed");
+        ui.add(TextEdit::singleline(&mut self.msg).hint_text("msg regex"));
+        ui.horizontal_wrapped(|ui| {
+            for kind in KINDS {
+                toggle(ui, &mut self.kinds, kind);
+            }
+        });
+        if !layers.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("layers");
+                for layer in layers {
+                    toggle(ui, &mut self.layers, layer);
+                }
+            });
+        }
+        ui.add(TextEdit::singleline(&mut self.color).hint_text("color, e.g. named(red)"));
+        ui.add(TextEdit::singleline(&mut self.ids).hint_text("ids, e.g. 3, 5-10"));
+        if !attrs.is_empty() {
+            ui.horizontal(|ui| {
+                ComboBox::from_id_salt("filter attr")
+                    .selected_text(self.attr.as_deref().unwrap_or("any attribute"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.attr, None, "any attribute");
+                        for name in attrs {
+                            ui.selectable_value(&mut self.attr, Some(name.clone()), name);
+                        }
+                    });
+                if self.attr.is_some() {
+                    let (lo, hi) = &mut self.attr_range;
+                    ui.add(DragValue::new(lo).speed(0.01));
+                    ui.label("..=");
+                    ui.add(DragValue::new(hi).speed(0.01));
+                }
+            });
+        }
+        if before != (self.msg.clone(), self.color.clone(), self.ids.clone()) {
+            self.compiled = None;
+        }
+        self.prepare();
+        if let Some(Err(ref e)) = self.compiled {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+    }
+}
+
+/// A checkbox for whether `list` contains `name`.
+fn toggle(ui: &mut Ui, list: &mut Vec<String>, name: &str) {
+    let mut on = list.iter().any(|n| n == name);
+    if ui.checkbox(&mut on, name).changed() {
+        if on {
+            list.push(name.to_owned());
+        }
+        else {
+            list.retain(|n| n != name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::PaintFrame;
+    use visualizer_shapes::{ pos, Circle, Color, Frame, Legend, Text };
+
+    /// A red hit, a blue miss, a text hit and a legend.
+    fn frame() -> PaintFrame {
+        let frame = Frame::new(pos(0.0, 0.0), pos(10.0, 10.0))
+            .add_element(Circle::new(pos(1.0, 1.0), 1.0).fill(Color::new(255, 0, 0)).element().with_msg("hit 3").with_layer("a").with_id(3))
+            .add_element(Circle::new(pos(5.0, 5.0), 1.0).fill(Color::new(0, 0, 255)).element().with_msg("miss").with_id(7))
+            .add_element(Text::new("label", 1.0, pos(8.0, 8.0)).element().with_msg("hit 9").with_attr("w", 0.5))
+            .add_element(Legend::categorical().element());
+        PaintFrame::from_frame(frame, 0)
+    }
+
+    fn visibilities(filter: &mut Filter) -> Vec<Visibility> {
+        filter.prepare();
+        frame().elems.iter().map(|e| filter.visibility(e)).collect()
+    }
+
+    fn matching(f: impl FnOnce(&mut Filter)) -> Vec<bool> {
+        let mut filter = Filter { enabled: true, mode: Mode::Hide, ..Default::default() };
+        f(&mut filter);
+        visibilities(&mut filter).into_iter().map(|v| v == Visibility::Shown).collect()
+    }
+
+    #[test]
+    fn conditions_match_the_elements() {
+        assert_eq!(matching(|_| {}), vec![true; 4]);
+        assert_eq!(matching(|f| f.msg = "^hit".to_owned()), vec![true, false, true, true]);
+        assert_eq!(matching(|f| f.kinds = vec!["text".to_owned()]), vec![false, false, true, true]);
+        assert_eq!(matching(|f| f.layers = vec!["a".to_owned()]), vec![true, false, false, true]);
+        assert_eq!(matching(|f| f.color = "named(blue)".to_owned()), vec![false, true, false, true]);
+        assert_eq!(matching(|f| f.ids = "1-5".to_owned()), vec![true, false, false, true]);
+        assert_eq!(matching(|f| { f.attr = Some("w".to_owned()); f.attr_range = (0.0, 0.4) }), vec![false, false, false, true]);
+        assert_eq!(matching(|f| { f.attr = Some("w".to_owned()); f.attr_range = (0.4, 0.6) }), vec![false, false, true, true]);
+        assert_eq!(matching(|f| { f.msg = "hit".to_owned(); f.ids = "3, 9".to_owned() }), vec![true, false, false, true]);
+    }
+
+    #[test]
+    fn elements_are_shown_dimmed_or_hidden() {
+        use Visibility::*;
+        let mut filter = Filter { enabled: true, msg: "miss".to_owned(), ..Default::default() };
+        assert_eq!(visibilities(&mut filter), vec![Dimmed, Shown, Dimmed, Shown]);
+        filter.mode = Mode::Hide;
+        assert_eq!(visibilities(&mut filter), vec![Hidden, Shown, Hidden, Shown]);
+        filter.invert = true;
+        assert_eq!(visibilities(&mut filter), vec![Shown, Hidden, Shown, Shown]);
+        filter.enabled = false;
+        assert_eq!(visibilities(&mut filter), vec![Shown; 4]);
+    }
+
+    #[test]
+    fn conditions_that_do_not_compile_show_everything() {
+        for f in [
+            Filter { msg: "(".to_owned(), ..Default::default() },
+            Filter { color: "named(".to_owned(), ..Default::default() },
+            Filter { ids: "5-3".to_owned(), ..Default::default() },
+        ] {
+            let mut filter = Filter { enabled: true, mode: Mode::Hide, ..f };
+            assert_eq!(visibilities(&mut filter), vec![Visibility::Shown; 4]);
+            assert!(matches!(filter.compiled, Some(Err(_))));
+        }
+    }
+
+    #[test]
+    fn parses_ids_and_ranges() {
+        assert_eq!(parse_ids("3, 5-10,,7 - 7 ").unwrap(), vec![(3, 3), (5, 10), (7, 7)]);
+        assert_eq!(parse_ids(" ").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        for s in ["10-5", "x", "-3", "3-", "1-2-3", "18446744073709551616"] {
+            assert!(parse_ids(s).is_err(), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use eframe::egui::*;
+use eframe::epaint::ColorMode;
+
+use super::parser::{ ElementKind, FrameElement, PaintFrame, PaintTrack };
+
+/// An element, as track, frame and element index. The element index is the viewer's, which
+/// differs from `FrameElement::source_index` past a grid.
+pub type ElementRef = (usize, usize, usize);
+
+pub enum Action {
+    Select(ElementRef),
+    /// Selects the element and brings it into view.
+    Reveal(ElementRef),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Index,
+    Kind,
+    Layer,
+    Id,
+    Msg,
+}
+
+impl Column {
+    const ALL: [Column; 5] = [Column::Index, Column::Kind, Column::Layer, Column::Id, Column::Msg];
+
+    fn label(self) -> &'static str {
+        match self {
+            Column::Index => "#",
+            Column::Kind => "shape",
+            Column::Layer => "layer",
+            Column::Id => "id",
+            Column::Msg => "msg",
+        }
+    }
+
+    /// The width in characters, the msg taking the rest of the row.
+    fn width(self) -> usize {
+        match self {
+            Column::Index => 5,
+            Column::Kind => 7,
+            Column::Layer => 10,
+            Column::Id => 7,
+            Column::Msg => 0,
+        }
+    }
+}
+
+/// Pads or cuts `s` to `width` characters, or leaves it alone for width 0.
+fn cell(s: &str, width: usize) -> String {
+    if width == 0 {
+        return s.to_owned();
+    }
+    let mut s = s.chars().take(width - 1).collect::<String>();
+    s.extend(std::iter::repeat(' ').take(width - s.chars().count()));
+    s
+}
+
+fn hex(c: Color32) -> String {
+    let [r, g, b, a] = c.to_srgba_unmultiplied();
+    format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+}
+
+fn color_row(ui: &mut Ui, name: &str, c: Color32) {
+    ui.label(name);
+    ui.horizontal(|ui| {
+        let (rect, _) = ui.allocate_exact_size(vec2(12.0, 12.0), Sense::hover());
+        ui.painter().rect_filled(rect, 2.0, c);
+        ui.label(hex(c));
+    });
+    ui.end_row();
+}
+
+fn row(ui: &mut Ui, name: &str, value: impl Into<WidgetText>) {
+    ui.label(name);
+    ui.label(value);
+    ui.end_row();
+}
+
+fn point(p: Pos2) -> String {
+    format!("({}, {})", p.x, p.y)
+}
+
+/// Everything known about one element.
+fn details(ui: &mut Ui, elem: &FrameElement) {
+    let mut vertices: &[Pos2] = &[];
+    Grid::new("inspector details").num_columns(2).striped(true).show(ui, |ui| {
+        row(ui, "shape", elem.kind);
+        if let Some(ref layer) = elem.layer {
+            row(ui, "layer", layer);
+        }
+        if let Some(id) = elem.id {
+            row(ui, "id", id.to_string());
+        }
+        match elem.shape {
+            ElementKind::Shape(Shape::Path(ref p)) => {
+                row(ui, "closed", p.closed.to_string());
+                if p.fill != Color32::TRANSPARENT {
+                    color_row(ui, "fill", p.fill);
+                }
+                row(ui, "stroke width", p.stroke.width.to_string());
+                if let ColorMode::Solid(c) = p.stroke.color {
+                    color_row(ui, "stroke", c);
+                }
+                vertices = &p.points;
+            }
+            ElementKind::Shape(Shape::Circle(ref c)) => {
+                row(ui, "center", point(c.center));
+                row(ui, "radius", c.radius.to_string());
+                if c.fill != Color32::TRANSPARENT {
+                    color_row(ui, "fill", c.fill);
+                }
+                row(ui, "stroke width", c.stroke.width.to_string());
+                color_row(ui, "stroke", c.stroke.color);
+            }
+            ElementKind::Shape(_) => {}
+            ElementKind::Text(ref t) => {
+                row(ui, "text", &t.text);
+                row(ui, "position", point(t.pos));
+                row(ui, "size", t.size.to_string());
+                color_row(ui, "color", t.color);
+            }
+            ElementKind::Legend(ref l) => {
+                row(ui, "title", l.title.as_deref().unwrap_or(""));
+                row(ui, "corner", format!("{:?}", l.corner));
+            }
+        }
+        for (name, value) in &elem.attrs {
+            row(ui, name, value.to_string());
+        }
+    });
+    if !vertices.is_empty() {
+        CollapsingHeader::new(format!("vertices ({})", vertices.len())).show(ui, |ui| {
+            let row_height = ui.text_style_height(&TextStyle::Body);
+            ScrollArea::vertical().id_salt("inspector vertices").max_height(160.0).show_rows(ui, row_height, vertices.len(), |ui, range| {
+                for i in range {
+                    ui.label(format!("{}: {}", i, point(vertices[i])));
+                }
+            });
+        });
+    }
+    if let Some(msg) = elem.msg() {
+        ui.separator();
+        ui.label(msg);
+    }
+}
+
+/// Shows the selected element and lists the elements of the current frame.
+pub struct Inspector {
+    pub open: bool,
+    sort: Column,
+    descending: bool,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self { open: false, sort: Column::Index, descending: false }
+    }
+}
+
+impl Inspector {
+    fn sorted(&self, frame: &PaintFrame) -> Vec<usize> {
+        let elems = &frame.elems;
+        let mut order = (0..elems.len()).collect::<Vec<_>>();
+        match self.sort {
+            Column::Index => {}
+            Column::Kind => order.sort_by_key(|&i| elems[i].kind),
+            Column::Layer => order.sort_by(|&a, &b| elems[a].layer.cmp(&elems[b].layer)),
+            Column::Id => order.sort_by_key(|&i| elems[i].id),
+            Column::Msg => order.sort_by(|&a, &b| elems[a].msg().cmp(&elems[b].msg())),
+        }
+        if self.descending {
+            order.reverse();
+        }
+        order
+    }
+
+    /// `frame_idx` is the frame of the track `track_idx` on screen.
+    pub fn ui(&mut self, ui: &mut Ui, tracks: &[PaintTrack], track_idx: usize, frame_idx: usize, selected: Option<ElementRef>) -> Option<Action> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.heading("inspector");
+            if ui.small_button("x").on_hover_text("close").clicked() {
+                self.open = false;
+            }
+        });
+        let elem = |(t, f, e): ElementRef| tracks.get(t)?.frames.get(f)?.elems.get(e);
+        match selected.and_then(|s| Some((s, elem(s)?))) {
+            Some((s, elem)) => {
+                ui.horizontal(|ui| {
+                    let track = tracks.get(s.0).filter(|_| tracks.len() > 1).map_or(String::new(), |t| format!("{}, ", t.label()));
+                    ui.label(format!("{}frame {}, element {}", track, s.1, elem.source_index));
+                    if ui.small_button("show").on_hover_text("go to its frame and center it").clicked() {
+                        action = Some(Action::Reveal(s));
+                    }
+                });
+                details(ui, elem);
+            }
+            None => {
+                ui.label("click an element to select it");
+            }
+        }
+        ui.separator();
+
+        let Some(frame) = tracks.get(track_idx).and_then(|t| t.frames.get(frame_idx)) else { return action };
+        ui.label(format!("elements of frame {} ({})", frame_idx, frame.elems.len()));
+        ui.horizontal(|ui| {
+            for column in Column::ALL {
+                let mut text = cell(column.label(), column.width());
+                if column == self.sort {
+                    text = format!("{}{}", column.label(), if self.descending { "v" } else { "^" });
+                    text = cell(&text, column.width());
+                }
+                if ui.selectable_label(column == self.sort, RichText::new(text).monospace()).clicked() {
+                    if column == self.sort {
+                        self.descending = !self.descending;
+                    }
+                    else {
+                        self.sort = column;
+                        self.descending = false;
+                    }
+                }
+            }
+        });
+        let order = self.sorted(frame);
+        let row_height = ui.text_style_height(&TextStyle::Monospace).max(ui.spacing().interact_size.y);
+        ScrollArea::both().id_salt("inspector elements").auto_shrink([false, true]).show_rows(ui, row_height, order.len(), |ui, range| {
+            for &i in &order[range] {
+                let e = &frame.elems[i];
+                let text = [
+                    cell(&e.source_index.to_string(), Column::Index.width()),
+                    cell(e.kind, Column::Kind.width()),
+                    cell(e.layer.as_deref().unwrap_or("-"), Column::Layer.width()),
+                    cell(&e.id.map_or("-".to_owned(), |id| id.to_string()), Column::Id.width()),
+                    e.msg().and_then(|m| m.lines().next()).unwrap_or("").to_owned(),
+                ].concat();
+                let r = (track_idx, frame_idx, i);
+                if ui.selectable_label(selected == Some(r), RichText::new(text).monospace()).clicked() {
+                    action = Some(Action::Select(r));
+                }
+            }
+        });
+        action
+    }
+}
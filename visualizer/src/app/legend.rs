@@ -0,0 +1,98 @@
+use eframe::egui::*;
+use visualizer_shapes::{ Colormap, Corner, Legend, LegendKind };
+
+const PAD: f32 = 6.0;
+const GAP: f32 = 4.0;
+const BAR: Vec2 = vec2(14.0, 120.0);
+const SWATCH: f32 = 12.0;
+
+pub fn color32(c: visualizer_shapes::Color) -> Color32 {
+    Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a)
+}
+
+pub fn anchor(corner: Corner) -> Align2 {
+    match corner {
+        Corner::TopLeft => Align2::LEFT_TOP,
+        Corner::TopRight => Align2::RIGHT_TOP,
+        Corner::BottomLeft => Align2::LEFT_BOTTOM,
+        Corner::BottomRight => Align2::RIGHT_BOTTOM,
+    }
+}
+
+fn galley(painter: &Painter, text: &str) -> std::sync::Arc<Galley> {
+    painter.layout_no_wrap(text.to_owned(), FontId::proportional(12.0), Color32::BLACK)
+}
+
+/// Legends in each corner of the canvas, stacked away from the corner so they do not overlap.
+pub struct Legends<'a> {
+    painter: &'a Painter,
+    areas: [Rect; 4],
+}
+
+impl<'a> Legends<'a> {
+    pub fn new(painter: &'a Painter, area: Rect) -> Self {
+        Self { painter, areas: [area.shrink(PAD); 4] }
+    }
+
+    /// Draws the background of a legend with content of `size`, and returns the content rect.
+    fn panel(&mut self, corner: Corner, title: Option<&str>, size: Vec2) -> Rect {
+        let title = title.map(|t| galley(self.painter, t));
+        let title_size = title.as_ref().map_or(Vec2::ZERO, |t| t.size() + vec2(0.0, GAP));
+        let size = vec2(size.x.max(title_size.x), size.y + title_size.y);
+        let anchor = anchor(corner);
+        let area = &mut self.areas[corner as usize];
+        let rect = anchor.align_size_within_rect(size + vec2(PAD, PAD) * 2.0, *area);
+        if anchor.y() == Align::Min {
+            area.min.y = rect.max.y + GAP;
+        }
+        else {
+            area.max.y = rect.min.y - GAP;
+        }
+        self.painter.rect(rect, 2.0, Color32::from_white_alpha(220), Stroke::new(1.0, Color32::GRAY));
+        let inner = rect.shrink(PAD);
+        if let Some(t) = title {
+            self.painter.galley(inner.min, t, Color32::BLACK);
+        }
+        Rect::from_min_max(inner.min + vec2(0.0, title_size.y), inner.max)
+    }
+
+    /// A vertical colorbar with `hi` at the top and `lo` at the bottom.
+    pub fn colorbar(&mut self, corner: Corner, title: Option<&str>, colormap: Colormap, lo: &str, hi: &str) {
+        let (lo, hi) = (galley(self.painter, lo), galley(self.painter, hi));
+        let labels = lo.size().x.max(hi.size().x);
+        let inner = self.panel(corner, title, vec2(BAR.x + GAP + labels, BAR.y));
+        let bar = Rect::from_min_size(inner.min, BAR);
+        const SLICES: usize = 64;
+        for i in 0..SLICES {
+            let y0 = bar.bottom() - bar.height() * i as f32 / SLICES as f32;
+            let y1 = bar.bottom() - bar.height() * (i + 1) as f32 / SLICES as f32;
+            let c = colormap.eval((i as f32 + 0.5) / SLICES as f32);
+            self.painter.rect_filled(Rect::from_x_y_ranges(bar.x_range(), y1..=y0), 0.0, color32(c));
+        }
+        self.painter.rect_stroke(bar, 0.0, Stroke::new(1.0, Color32::GRAY));
+        self.painter.galley(pos2(bar.right() + GAP, bar.top()), hi, Color32::BLACK);
+        self.painter.galley(pos2(bar.right() + GAP, bar.bottom() - lo.size().y), lo, Color32::BLACK);
+    }
+
+    /// A swatch and a label per row.
+    pub fn entries(&mut self, corner: Corner, title: Option<&str>, entries: &[(visualizer_shapes::Color, String)]) {
+        let labels = entries.iter().map(|(_, l)| galley(self.painter, l)).collect::<Vec<_>>();
+        let row = labels.iter().map(|g| g.size().y).fold(SWATCH, f32::max);
+        let width = labels.iter().map(|g| g.size().x).fold(0.0, f32::max);
+        let height = (row + GAP) * entries.len() as f32 - if entries.is_empty() { 0.0 } else { GAP };
+        let inner = self.panel(corner, title, vec2(SWATCH + GAP + width, height));
+        for (i, ((color, _), label)) in entries.iter().zip(labels).enumerate() {
+            let top = inner.top() + (row + GAP) * i as f32;
+            let swatch = Rect::from_min_size(pos2(inner.left(), top + (row - SWATCH) / 2.0), vec2(SWATCH, SWATCH));
+            self.painter.rect(swatch, 0.0, color32(*color), Stroke::new(1.0, Color32::GRAY));
+            self.painter.galley(pos2(swatch.right() + GAP, top + (row - label.size().y) / 2.0), label, Color32::BLACK);
+        }
+    }
+
+    pub fn legend(&mut self, legend: &Legend) {
+        match legend.kind {
+            LegendKind::Colormap { colormap, ref min, ref max } => self.colorbar(legend.corner, legend.title.as_deref(), colormap, min, max),
+            LegendKind::Categorical(ref entries) => self.entries(legend.corner, legend.title.as_deref(), entries),
+        }
+    }
+}
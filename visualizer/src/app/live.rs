@@ -0,0 +1,265 @@
+use eframe::egui::*;
+use std::io::{ BufReader, Read };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ channel, Receiver, Sender };
+use std::sync::Arc;
+use std::time::Duration;
+use visualizer_shapes::live::{ read_packets, Endpoint, Packet };
+use super::parser::read_input;
+
+enum Event {
+    Status(String),
+    /// The listener gave up, e.g. because the address is in use.
+    Failed(String),
+    Packet(Packet),
+}
+
+struct Running {
+    stop: Arc<AtomicBool>,
+    events: Receiver<Event>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Listens for `LiveClient`s and hands their packets to the app.
+pub struct Live {
+    pub address: String,
+    /// Jumps to every new frame of the selected track.
+    pub follow: bool,
+    status: String,
+    running: Option<Running>,
+    /// Frames decoded from stdin as they arrive.
+    stdin: Option<Receiver<Event>>,
+}
+
+impl Default for Live {
+    fn default() -> Self {
+        Self {
+            address: Endpoint::DEFAULT.to_owned(),
+            follow: true,
+            status: String::new(),
+            running: None,
+            stdin: None,
+        }
+    }
+}
+
+/// Reads one connection to its end. Packets arriving after the app stopped listening are dropped.
+fn read_stream(stream: impl Read, peer: String, tx: Sender<Event>, ctx: Context) {
+    let _ = tx.send(Event::Status(format!("connected: {}", peer)));
+    let res = read_packets(BufReader::new(stream), |packet| {
+        if tx.send(Event::Packet(packet)).is_ok() {
+            ctx.request_repaint();
+        }
+    });
+    let status = match res {
+        Ok(()) => format!("disconnected: {}", peer),
+        Err(e) => format!("{}: {:?}", peer, e),
+    };
+    let _ = tx.send(Event::Status(status));
+    ctx.request_repaint();
+}
+
+/// Accepts connections until `stop`, reading each on its own thread. `accept` must not block.
+fn accept_loop<S: Read + Send + 'static>(
+    mut accept: impl FnMut() -> std::io::Result<(S, String)>,
+    stop: &AtomicBool,
+    tx: &Sender<Event>,
+    ctx: &Context,
+) -> std::io::Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        match accept() {
+            Ok((stream, peer)) => {
+                let (tx, ctx) = (tx.clone(), ctx.clone());
+                std::thread::spawn(move || read_stream(stream, peer, tx, ctx));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Removes a socket file left by an earlier run, which would make bind fail. Anything else
+/// at `path` is left alone.
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Err(std::io::Error::new(std::io::ErrorKind::AddrInUse, format!("{} is in use and not a socket", path.display()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The device and inode of the file at `path`, to tell whether it is still the one created here.
+#[cfg(unix)]
+fn file_id(path: &std::path::Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+fn listen(endpoint: Endpoint, stop: Arc<AtomicBool>, tx: Sender<Event>, ctx: Context) -> std::io::Result<()> {
+    match endpoint {
+        Endpoint::Tcp(ref addr) => {
+            let listener = std::net::TcpListener::bind(addr)?;
+            listener.set_nonblocking(true)?;
+            let _ = tx.send(Event::Status(format!("listening on {}", endpoint)));
+            accept_loop(|| {
+                let (stream, peer) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok((stream, peer.to_string()))
+            }, &stop, &tx, &ctx)
+        }
+        #[cfg(unix)]
+        Endpoint::Unix(ref path) => {
+            remove_stale_socket(path)?;
+            let listener = std::os::unix::net::UnixListener::bind(path)?;
+            let created = file_id(path);
+            listener.set_nonblocking(true)?;
+            let _ = tx.send(Event::Status(format!("listening on {}", endpoint)));
+            let res = accept_loop(|| {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok((stream, endpoint.to_string()))
+            }, &stop, &tx, &ctx);
+            if created.is_some() && file_id(path) == created {
+                let _ = std::fs::remove_file(path);
+            }
+            res
+        }
+        #[cfg(not(unix))]
+        Endpoint::Unix(_) => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "unix domain sockets are not supported on this platform")),
+    }
+}
+
+impl Live {
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn start(&mut self, ctx: &Context) {
+        if cfg!(target_arch = "wasm32") {
+            self.status = "live mode needs the native viewer".to_owned();
+            return;
+        }
+        let (tx, events) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let endpoint = Endpoint::parse(&self.address);
+        let (thread_stop, ctx) = (stop.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            if let Err(e) = listen(endpoint, thread_stop, tx.clone(), ctx.clone()) {
+                let _ = tx.send(Event::Failed(format!("listen failed: {}", e)));
+                ctx.request_repaint();
+            }
+        });
+        self.running = Some(Running { stop, events });
+    }
+
+    /// Reads frames piped into stdin in any format `read_input` knows, showing every frame as
+    /// soon as it is decoded.
+    pub fn read_stdin(&mut self, ctx: &Context) {
+        let (tx, events) = channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let res = read_input(BufReader::new(std::io::stdin().lock()), |packet| {
+                let _ = tx.send(Event::Packet(packet));
+                ctx.request_repaint();
+            });
+            let status = match res {
+                Ok(()) => "stdin closed".to_owned(),
+                Err(e) => format!("stdin: {:?}", e),
+            };
+            let _ = tx.send(Event::Status(status));
+            ctx.request_repaint();
+        });
+        self.status = "reading stdin".to_owned();
+        self.stdin = Some(events);
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add_enabled(!self.is_running(), TextEdit::singleline(&mut self.address).desired_width(140.0))
+                .on_hover_text("host:port, or unix:/path/to/socket");
+            if self.is_running() {
+                if ui.button("stop").clicked() {
+                    self.running = None;
+                    self.status = "stopped".to_owned();
+                }
+            }
+            else if ui.button("listen").clicked() {
+                self.start(ui.ctx());
+            }
+        });
+        ui.checkbox(&mut self.follow, "follow newest frame");
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+
+    /// The packets received since the last call.
+    pub fn poll(&mut self) -> Vec<Packet> {
+        let mut packets = vec![];
+        let mut failed = false;
+        if let Some(ref running) = self.running {
+            for event in running.events.try_iter() {
+                match event {
+                    Event::Status(s) => self.status = s,
+                    Event::Failed(s) => {
+                        self.status = s;
+                        failed = true;
+                    }
+                    Event::Packet(p) => packets.push(p),
+                }
+            }
+        }
+        if failed {
+            self.running = None;
+        }
+        if let Some(ref stdin) = self.stdin {
+            for event in stdin.try_iter() {
+                match event {
+                    Event::Packet(p) => packets.push(p),
+                    Event::Status(s) | Event::Failed(s) => self.status = s,
+                }
+            }
+        }
+        packets
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("visualizer-live-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn listening_leaves_other_files_alone() {
+        let path = temp_path("file");
+        std::fs::write(&path, "keep me").unwrap();
+        let (tx, _rx) = channel();
+        let res = listen(Endpoint::Unix(path.clone()), Arc::new(AtomicBool::new(false)), tx, Context::default());
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::AddrInUse);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stale_sockets_are_replaced_and_removed_after() {
+        let path = temp_path("socket");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let (tx, rx) = channel();
+        // stopped from the start, so it binds and returns right away
+        listen(Endpoint::Unix(path.clone()), Arc::new(AtomicBool::new(true)), tx, Context::default()).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(Event::Status(_))));
+        assert!(!path.exists());
+    }
+}
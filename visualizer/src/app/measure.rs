@@ -0,0 +1,87 @@
+use eframe::egui::*;
+use eframe::emath::RectTransform;
+
+use super::parser::{ ElementKind, FrameElement };
+
+/// How close, in screen points, the pointer must be to a vertex to snap to it.
+const SNAP_RADIUS: f32 = 10.0;
+
+const COLOR: Color32 = Color32::from_rgb(0, 140, 255);
+
+/// The points of an element a measurement can snap to.
+fn vertices(elem: &FrameElement) -> Vec<Pos2> {
+    match elem.shape {
+        ElementKind::Shape(Shape::Path(ref p)) => p.points.clone(),
+        ElementKind::Shape(Shape::Circle(ref c)) => vec![c.center],
+        ElementKind::Text(ref t) => vec![t.pos],
+        _ => vec![],
+    }
+}
+
+/// The distance, dx, dy and angle between two world points, for the status bar.
+pub fn describe(a: Pos2, b: Pos2) -> String {
+    let d = b - a;
+    // world y points down, so a positive angle turns clockwise on screen
+    format!("distance {:.3}  dx {:.3}  dy {:.3}  angle {:.1}°", d.length(), d.x, d.y, d.y.atan2(d.x).to_degrees())
+}
+
+/// Measures between two clicked points, optionally snapped to element vertices.
+#[derive(Default)]
+pub struct Measure {
+    pub active: bool,
+    pub snap: bool,
+    /// The first point, and the second once it is clicked.
+    points: Vec<Pos2>,
+}
+
+impl Measure {
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// The world point under `pointer`, snapped to the nearest vertex of `elems` in reach.
+    pub fn target<'a>(&self, pointer: Pos2, to_screen: &RectTransform, elems: impl Iterator<Item = &'a FrameElement>) -> Pos2 {
+        let world = to_screen.inverse() * pointer;
+        if !self.snap {
+            return world;
+        }
+        elems.flat_map(vertices)
+            .map(|v| (v, (*to_screen * v).distance(pointer)))
+            .filter(|&(_, d)| d <= SNAP_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(world, |(v, _)| v)
+    }
+
+    /// Adds a clicked point, starting over after two.
+    pub fn click(&mut self, p: Pos2) {
+        if self.points.len() == 2 {
+            self.points.clear();
+        }
+        self.points.push(p);
+    }
+
+    /// The measured segment, ending at `hover` while the second point is not clicked yet.
+    pub fn segment(&self, hover: Option<Pos2>) -> Option<(Pos2, Pos2)> {
+        match self.points[..] {
+            [a, b] => Some((a, b)),
+            [a] => Some((a, hover?)),
+            _ => None,
+        }
+    }
+
+    pub fn paint(&self, painter: &Painter, to_screen: &RectTransform, hover: Option<Pos2>) {
+        if let Some(p) = hover.filter(|_| self.active) {
+            painter.circle_stroke(*to_screen * p, 4.0, Stroke::new(1.0, COLOR));
+        }
+        let Some((a, b)) = self.segment(hover) else { return };
+        let (sa, sb) = (*to_screen * a, *to_screen * b);
+        painter.line_segment([sa, sb], Stroke::new(2.0, COLOR));
+        painter.circle_filled(sa, 3.0, COLOR);
+        painter.circle_filled(sb, 3.0, COLOR);
+        let label = format!("{:.3}", (b - a).length());
+        let galley = painter.layout_no_wrap(label, FontId::proportional(12.0), Color32::WHITE);
+        let rect = Rect::from_center_size(sa.lerp(sb, 0.5) + vec2(0.0, -12.0), galley.size()).expand(2.0);
+        painter.rect_filled(rect, 2.0, COLOR);
+        painter.galley(rect.min + vec2(2.0, 2.0), galley, Color32::WHITE);
+    }
+}
@@ -1,9 +1,11 @@
 pub mod color;
-use color::*;
 
 use eframe::emath::RectTransform;
 use eframe::egui::*;
 use eframe::epaint::{CircleShape, PathShape};
+use std::io::{ BufRead, BufReader, Read };
+use visualizer_shapes::live::{ read_packets, Packet };
+use visualizer_shapes::Diagnostic;
 
 #[derive(Debug)]
 pub enum HoverCondition {
@@ -26,7 +28,7 @@ impl HoverCondition {
                     let a2 = to_screen * path[i];
                     let dist = 
                         if (a2 - a1).dot(p - a1) < 0.0 || (a1 - a2).dot(p - a2) < 0.0 {
-                            std::f32::INFINITY
+                            f32::INFINITY
                         }
                         else {
                             let r = a1 + (a2 - a1).dot(p - a1) / (a2 - a1).length_sq() * (a2 - a1);
@@ -59,6 +61,8 @@ impl HoverCondition {
     }
 }
 
+/// Where an element can be hovered and clicked, and its tooltip. The msg is empty for
+/// elements without one, which can still be clicked.
 #[derive(Debug)]
 pub struct Hover {
     pub hover_cond: HoverCondition,
@@ -83,18 +87,66 @@ pub struct Text {
 pub enum ElementKind {
     Shape(Shape),
     Text(Text),
+    /// Drawn in screen space by `legend::Legends`.
+    Legend(visualizer_shapes::Legend),
 }
 
 #[derive(Debug)]
 pub struct FrameElement {
     pub shape: ElementKind,
     pub hover: Option<Hover>,
+    pub attrs: Vec<(String, f32)>,
+    /// The producer's shape: "path", "circle", "text", "grid" or "legend".
+    pub kind: &'static str,
+    pub layer: Option<String>,
+    pub id: Option<u64>,
+    /// The index of the producer's element this was made from. The cells of a grid share it.
+    pub source_index: usize,
+}
+
+impl FrameElement {
+    pub fn attr(&self, name: &str) -> Option<f32> {
+        self.attrs.iter().find(|a| a.0 == name).map(|a| a.1)
+    }
+    /// The fill and the stroke color, where the element has them.
+    pub fn colors(&self) -> [Option<Color32>; 2] {
+        let fill = |c: Color32| Some(c).filter(|c| *c != Color32::TRANSPARENT);
+        match self.shape {
+            ElementKind::Shape(Shape::Path(ref p)) => {
+                let stroke = match p.stroke.color {
+                    epaint::ColorMode::Solid(c) => Some(c),
+                    _ => None,
+                };
+                [fill(p.fill), stroke]
+            }
+            ElementKind::Shape(Shape::Circle(ref c)) => [fill(c.fill), Some(c.stroke.color)],
+            ElementKind::Text(ref t) => [Some(t.color), None],
+            _ => [None, None],
+        }
+    }
+    /// The hover msg, if the element has one.
+    pub fn msg(&self) -> Option<&str> {
+        self.hover.as_ref().map(|h| h.msg.as_str()).filter(|m| !m.is_empty())
+    }
+    pub fn bounds(&self, fonts: &epaint::text::Fonts) -> Rect {
+        match self.shape {
+            ElementKind::Shape(ref shape) => shape.visual_bounding_rect(),
+            ElementKind::Text(ref text) => {
+                let galley = fonts.layout_no_wrap(text.text.clone(), FontId::proportional(text.size), text.color);
+                Rect::from_center_size(text.pos, galley.rect.size())
+            }
+            ElementKind::Legend(_) => Rect::NOTHING,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct PaintFrame {
     pub elems: Vec<FrameElement>,
     pub rect: Rect,
+    pub step: u64,
+    pub keyframe: bool,
+    pub events: Vec<String>,
 }
 
 impl Default for PaintFrame {
@@ -102,65 +154,358 @@ impl Default for PaintFrame {
         PaintFrame {
             elems: Vec::new(),
             rect: Rect::NOTHING,
+            step: 0,
+            keyframe: false,
+            events: vec![],
         }
     }
 }
 
 impl PaintFrame {
-    fn makeup(frames: visualizer_shapes::Frames) -> anyhow::Result<Vec<Self>> {
-        Ok(frames.frames.into_iter().map(|frame| {
-            let elems = frame.elems.into_iter().map(|e| {
-                //eprintln!("{:?}", e);
-                match e.shape {
-                    visualizer_shapes::Shape::Path(p) => {
-                        let vp = p.vp.into_iter().map(|p| pos2(p.x, p.y)).collect::<Vec<_>>();
-                        let closed = p.fill.is_some();
+    /// The union of the element bounds, ignoring elements with non-finite coordinates.
+    pub fn content_rect(&self, fonts: &epaint::text::Fonts) -> Rect {
+        self.elems.iter()
+            .map(|e| e.bounds(fonts))
+            .filter(|r| r.is_finite())
+            .fold(Rect::NOTHING, |acc, r| acc.union(r))
+    }
+    /// Sets the hover rect of the text elements to their galley bounds, which need the fonts.
+    pub fn measure_texts(&mut self, fonts: &epaint::text::Fonts) {
+        for e in self.elems.iter_mut().filter(|e| matches!(e.shape, ElementKind::Text(_))) {
+            let bounds = e.bounds(fonts);
+            if let Some(Hover { hover_cond: HoverCondition::Rect(ref mut rect), .. }) = e.hover {
+                if *rect == Rect::NOTHING {
+                    *rect = bounds;
+                }
+            }
+        }
+    }
+    /// Converts frame `i` of a track; `i` is the step of frames that do not carry one.
+    pub fn from_frame(frame: visualizer_shapes::Frame, i: usize) -> Self {
+        let elems = frame.elems.into_iter().enumerate().flat_map(|(source_index, e)| {
+            //eprintln!("{:?}", e);
+            match e.shape {
+                visualizer_shapes::Shape::Path(p) => {
+                    let vp = p.vp.into_iter().map(|p| pos2(p.x, p.y)).collect::<Vec<_>>();
+                    let closed = p.fill.is_some();
+                    vec![FrameElement {
+                        shape: ElementKind::Shape(Shape::Path(PathShape {
+                                       points: vp.clone(),
+                                       closed,
+                                       fill: p.fill.map(|f| Color32::from_rgba_unmultiplied(f.r, f.g, f.b, f.a)).unwrap_or(Color32::TRANSPARENT),
+                                       stroke: Stroke::new(p.stroke.width, Color32::from_rgba_unmultiplied(p.stroke.color.r, p.stroke.color.g, p.stroke.color.b, p.stroke.color.a)).into(),
+                        })),
+                        hover: Some(Hover { msg: e.msg.unwrap_or_default(), hover_cond: if closed { HoverCondition::ClosedPath(vp) } else { HoverCondition::Path(vp) } }),
+                        attrs: e.attrs,
+                        kind: "path",
+                        layer: e.layer,
+                        id: e.id,
+                        source_index,
+                    }]
+                }
+                visualizer_shapes::Shape::Circle(c) => {
+                    vec![FrameElement {
+                        shape: ElementKind::Shape(Shape::Circle(CircleShape {
+                            center: pos2(c.center.x, c.center.y),
+                            radius: c.radius,
+                            fill: c.fill.map(|f| Color32::from_rgba_unmultiplied(f.r, f.g, f.b, f.a)).unwrap_or(Color32::TRANSPARENT),
+                            stroke: c.stroke.map(|s| Stroke::new(s.width, Color32::from_rgba_unmultiplied(s.color.r, s.color.g, s.color.b, s.color.a))).unwrap_or(Stroke::default()),
+                        })),
+                        hover: Some(Hover { msg: e.msg.unwrap_or_default(), hover_cond: HoverCondition::Circle(pos2(c.center.x, c.center.y), c.radius) }),
+                        attrs: e.attrs,
+                        kind: "circle",
+                        layer: e.layer,
+                        id: e.id,
+                        source_index,
+                    }]
+                }
+                visualizer_shapes::Shape::Text(t) => {
+                    vec![FrameElement {
+                        shape: ElementKind::Text(Text {
+                            text: t.text,
+                            size: t.size,
+                            pos: pos2(t.pos.x, t.pos.y),
+                            color: Color32::from_rgba_unmultiplied(t.color.r, t.color.g, t.color.b, t.color.a),
+                        }),
+                        // measured once there are fonts, see `PaintFrame::measure_texts`
+                        hover: Some(Hover { msg: e.msg.unwrap_or_default(), hover_cond: HoverCondition::Rect(Rect::NOTHING) }),
+                        attrs: e.attrs,
+                        kind: "text",
+                        layer: e.layer,
+                        id: e.id,
+                        source_index,
+                    }]
+                }
+                visualizer_shapes::Shape::Legend(l) => {
+                    vec![FrameElement {
+                        shape: ElementKind::Legend(l),
+                        hover: None,
+                        attrs: e.attrs,
+                        kind: "legend",
+                        layer: e.layer,
+                        id: e.id,
+                        source_index,
+                    }]
+                }
+                visualizer_shapes::Shape::Grid(g) => {
+                    let stroke = Stroke::new(g.stroke.width, Color32::from_rgba_unmultiplied(g.stroke.color.r, g.stroke.color.g, g.stroke.color.b, g.stroke.color.a));
+                    g.cells.into_iter().map(|c| {
+                        let (i, j) = c.index;
+                        let vp = g.lattice.vertices(i, j).into_iter().map(|p| pos2(p.x, p.y)).collect::<Vec<_>>();
+                        let msg = e.msg.iter().cloned()
+                            .chain(std::iter::once(format!("({}, {})", i, j)))
+                            .chain(c.msg)
+                            .collect::<Vec<_>>()
+                            .join("\n");
                         FrameElement {
                             shape: ElementKind::Shape(Shape::Path(PathShape {
-                                           points: vp.clone(),
-                                           closed,
-                                           fill: p.fill.map(|f| Color32::from_rgba_unmultiplied(f.r, f.g, f.b, f.a)).unwrap_or(Color32::TRANSPARENT),
-                                           stroke: Stroke::new(p.stroke.width, Color32::from_rgba_unmultiplied(p.stroke.color.r, p.stroke.color.g, p.stroke.color.b, p.stroke.color.a)).into(),
-                            })),
-                            hover: e.msg.map(|msg| Hover { msg, hover_cond: if closed { HoverCondition::ClosedPath(vp) } else { HoverCondition::Path(vp) } })
-                        }
-                    }
-                    visualizer_shapes::Shape::Circle(c) => {
-                        FrameElement {
-                            shape: ElementKind::Shape(Shape::Circle(CircleShape {
-                                center: pos2(c.center.x, c.center.y),
-                                radius: c.radius,
+                                points: vp.clone(),
+                                closed: true,
                                 fill: c.fill.map(|f| Color32::from_rgba_unmultiplied(f.r, f.g, f.b, f.a)).unwrap_or(Color32::TRANSPARENT),
-                                stroke: c.stroke.map(|s| Stroke::new(s.width, Color32::from_rgba_unmultiplied(s.color.r, s.color.g, s.color.b, s.color.a))).unwrap_or(Stroke::default()),
+                                stroke: stroke.into(),
                             })),
-                            hover: e.msg.map(|msg| Hover { msg, hover_cond: HoverCondition::Circle(pos2(c.center.x, c.center.y), c.radius) })
-                        }
-                    }
-                    visualizer_shapes::Shape::Text(t) => {
-                        FrameElement {
-                            shape: ElementKind::Text(Text {
-                                text: t.text,
-                                size: t.size,
-                                pos: pos2(t.pos.x, t.pos.y),
-                                color: Color32::from_rgba_unmultiplied(t.color.r, t.color.g, t.color.b, t.color.a),
-                            }),
-                            hover: None,
+                            hover: Some(Hover { msg, hover_cond: HoverCondition::ClosedPath(vp) }),
+                            // a cell's attrs override the grid's, as `attr` finds the first
+                            attrs: {
+                                let grid = e.attrs.iter().filter(|a| !c.attrs.iter().any(|b| b.0 == a.0)).cloned().collect::<Vec<_>>();
+                                c.attrs.into_iter().chain(grid).collect()
+                            },
+                            kind: "grid",
+                            layer: e.layer.clone(),
+                            id: e.id,
+                            source_index,
                         }
-                    }
+                    }).collect()
                 }
-            }).collect::<Vec<_>>();
-            PaintFrame {
-                elems,
-                rect: Rect::from_two_pos(pos2(frame.p1.x, frame.p1.y), pos2(frame.p2.x, frame.p2.y)),
             }
-        }).collect())
+        }).collect::<Vec<_>>();
+        PaintFrame {
+            elems,
+            rect: Rect::from_two_pos(pos2(frame.p1.x, frame.p1.y), pos2(frame.p2.x, frame.p2.y)),
+            step: frame.step.unwrap_or(i as u64),
+            keyframe: frame.keyframe,
+            events: frame.events,
+        }
     }
-    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Vec<Self>> {
-        let frames = visualizer_shapes::Frames::decode_from_file(path)?;
-        Self::makeup(frames)
+    fn makeup(frames: Vec<visualizer_shapes::Frame>) -> anyhow::Result<Vec<Self>> {
+        Ok(frames.into_iter().enumerate().map(|(i, frame)| Self::from_frame(frame, i)).collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct PaintTrack {
+    pub name: Option<String>,
+    pub frames: Vec<PaintFrame>,
+}
+
+impl PaintTrack {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or("main")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PaintData {
+    pub tracks: Vec<PaintTrack>,
+    pub series: Vec<visualizer_shapes::Series>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The attribute names used by any element, sorted.
+    pub attrs: Vec<String>,
+    /// The layers of all elements, sorted.
+    pub layers: Vec<String>,
+}
+
+/// The distinct names in `names`, sorted.
+fn sorted<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    names.collect::<std::collections::BTreeSet<_>>().into_iter().map(String::from).collect()
+}
+
+impl PaintData {
+    fn makeup(frames: visualizer_shapes::Frames) -> anyhow::Result<Self> {
+        let diagnostics = frames.validate();
+        let mut tracks = vec![];
+        if !frames.frames.is_empty() || frames.tracks.is_empty() {
+            tracks.push(PaintTrack { name: None, frames: PaintFrame::makeup(frames.frames)? });
+        }
+        for track in frames.tracks {
+            tracks.push(PaintTrack { name: Some(track.name), frames: PaintFrame::makeup(track.frames)? });
+        }
+        let elems = || tracks.iter().flat_map(|t| t.frames.iter()).flat_map(|f| f.elems.iter());
+        let attrs = sorted(elems().flat_map(|e| e.attrs.iter().map(|a| a.0.as_str())));
+        let layers = sorted(elems().filter_map(|e| e.layer.as_deref()));
+        Ok(PaintData { tracks, series: frames.series, diagnostics, attrs, layers })
     }
-    pub fn from_u8s(v: Vec<u8>) -> anyhow::Result<Vec<Self>> {
-        let frames = visualizer_shapes::Frames::decode(v)?;
+    fn read(reader: impl Read) -> anyhow::Result<Self> {
+        let mut frames = visualizer_shapes::Frames::new();
+        read_input(reader, |p| frames.push_packet(p))?;
         Self::makeup(frames)
     }
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> anyhow::Result<Self> {
+        Self::read(std::fs::File::open(path)?)
+    }
+    pub fn from_u8s(v: Vec<u8>) -> anyhow::Result<Self> {
+        Self::read(&v[..])
+    }
+}
+
+/// A value of a JSON stream.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Json {
+    Packet(Packet),
+    Frames(visualizer_shapes::Frames),
+    Frame(visualizer_shapes::Frame),
+}
+
+fn read_json(reader: impl Read, mut f: impl FnMut(Packet)) -> anyhow::Result<()> {
+    for value in serde_json::Deserializer::from_reader(reader).into_iter::<Json>() {
+        match value? {
+            Json::Packet(p) => f(p),
+            Json::Frame(frame) => f(Packet::Frame { track: None, frame }),
+            Json::Frames(frames) => {
+                for frame in frames.frames {
+                    f(Packet::Frame { track: None, frame });
+                }
+                for track in frames.tracks {
+                    for frame in track.frames {
+                        f(Packet::Frame { track: Some(track.name.clone()), frame });
+                    }
+                }
+                for series in frames.series {
+                    f(Packet::Series(series));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads frames in either format, handing every packet to `f` as soon as it is decoded: the
+/// binary encoding, or a stream of JSON values that are each a `Packet`, a `Frames` or a `Frame`.
+pub fn read_input(mut reader: impl Read, f: impl FnMut(Packet)) -> anyhow::Result<()> {
+    let mut head = [0u8; 8];
+    let mut n = 0;
+    while n < head.len() {
+        match reader.read(&mut head[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let head = &head[..n];
+    // the binary encoding starts with its magic, the baseline one with a u64 frame count
+    // whose high bytes are zero
+    if head.starts_with(&visualizer_shapes::format::MAGIC) || head.contains(&0) {
+        return read_packets(head.chain(reader), f);
+    }
+    let mut reader = BufReader::new(head.chain(reader));
+    loop {
+        let buf = reader.fill_buf()?;
+        match buf.iter().position(|c| !c.is_ascii_whitespace()) {
+            Some(i) => {
+                if buf[i] != b'{' {
+                    anyhow::bail!("the input is neither the visualizer_shapes encoding nor JSON");
+                }
+                reader.consume(i);
+                return read_json(reader, f);
+            }
+            None if buf.is_empty() => return Ok(()),
+            None => {
+                let n = buf.len();
+                reader.consume(n);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visualizer_shapes::{ pos, Circle, Frames, Grid, GridCell, Lattice, Series, Track };
+
+    fn frame(x: f32) -> visualizer_shapes::Frame {
+        visualizer_shapes::Frame::new(pos(0.0, 0.0), pos(x, x)).add_element(Circle::new(pos(0.0, 0.0), 1.0).element())
+    }
+
+    fn frames() -> Frames {
+        Frames::new()
+            .add_frame(frame(1.0))
+            .add_track(Track::new("t").add_frame(frame(2.0)))
+            .add_series(Series::new("s").add_sample(0, 1.0))
+    }
+
+    fn read(bytes: &[u8]) -> Frames {
+        let mut frames = Frames::new();
+        read_input(bytes, |p| frames.push_packet(p)).unwrap();
+        frames
+    }
+
+    #[test]
+    fn reads_every_format() {
+        let expected = format!("{:?}", frames());
+        assert_eq!(format!("{:?}", read(&frames().encode().unwrap())), expected);
+        assert_eq!(format!("{:?}", read(serde_json::to_string(&frames()).unwrap().as_bytes())), expected);
+    }
+
+    #[test]
+    fn rejects_other_input() {
+        for input in ["circle 0 0 1\n", "  hello", "[1, 2]"] {
+            assert!(read_input(input.as_bytes(), |_| {}).is_err(), "{}", input);
+        }
+        // a corrupted JSON stream is an error, not an empty frame
+        let json = serde_json::to_string(&frame(1.0)).unwrap();
+        assert!(read_input(&json.as_bytes()[..json.len() / 2], |_| {}).is_err());
+        assert!(read_input(format!("{} {{\"oops\": 1}}", json).as_bytes(), |_| {}).is_err());
+    }
+
+    #[test]
+    fn reads_a_stream_of_json_values() {
+        let packet = Packet::Frame { track: Some("t".to_owned()), frame: frame(2.0) };
+        let json = format!(
+            "  \n{} {}\n{}",
+            serde_json::to_string(&frame(1.0)).unwrap(),
+            serde_json::to_string(&packet).unwrap(),
+            serde_json::to_string(&Packet::Series(Series::new("s").add_sample(0, 1.0))).unwrap(),
+        );
+        assert_eq!(format!("{:?}", read(json.as_bytes())), format!("{:?}", frames()));
+    }
+
+    #[test]
+    fn tells_a_short_baseline_encoding_from_whitespace() {
+        // a frame count of 10 starts with a newline, which must not pass for leading whitespace:
+        // the frames are missing, so the binary decoding fails
+        assert!(read_input(&10u64.to_le_bytes()[..], |_| {}).is_err());
+        assert_eq!(read(&[]).frames.len(), 0);
+        assert_eq!(read(b"   \n ").frames.len(), 0);
+    }
+
+    #[test]
+    fn grid_cells_keep_the_grid_index_and_override_its_attrs() {
+        let grid = Grid::new(Lattice::isometric(pos(0.0, 0.0), 1.0))
+            .add_cell(GridCell::new(0, 0).with_attr("a", 2.0))
+            .add_cell(GridCell::new(1, 0));
+        let frame = visualizer_shapes::Frame::new(pos(0.0, 0.0), pos(1.0, 1.0))
+            .add_element(grid.element().with_attr("a", 1.0).with_attr("b", 3.0))
+            .add_element(Circle::new(pos(0.0, 0.0), 1.0).element());
+        let frame = PaintFrame::from_frame(frame, 0);
+        assert_eq!(frame.elems.iter().map(|e| e.source_index).collect::<Vec<_>>(), vec![0, 0, 1]);
+        assert_eq!((frame.elems[0].attr("a"), frame.elems[0].attr("b")), (Some(2.0), Some(3.0)));
+        assert_eq!(frame.elems[0].attrs.len(), 2);
+        assert_eq!(frame.elems[1].attr("a"), Some(1.0));
+    }
+
+    #[test]
+    fn texts_hover_over_their_galley() {
+        let frame = visualizer_shapes::Frame::new(pos(0.0, 0.0), pos(10.0, 10.0))
+            .add_element(visualizer_shapes::Text::new("label", 1.0, pos(5.0, 5.0)).element().with_msg("hi"));
+        let mut frame = PaintFrame::from_frame(frame, 0);
+        let fonts = epaint::text::Fonts::new(1.0, 1024, FontDefinitions::default());
+        frame.measure_texts(&fonts);
+        let to_screen = RectTransform::from_to(Rect::from_min_size(Pos2::ZERO, vec2(10.0, 10.0)), Rect::from_min_size(Pos2::ZERO, vec2(100.0, 100.0)));
+        let hover = frame.elems[0].hover.as_ref().unwrap();
+        assert_eq!(frame.elems[0].msg(), Some("hi"));
+        assert!(hover.check(pos2(50.0, 50.0), &to_screen));
+        assert!(!hover.check(pos2(50.0, 70.0), &to_screen));
+    }
 }
@@ -1,52 +1,206 @@
-use eframe::epaint::ecolor::rgb_from_hsv;
+//! The text color syntax. Every function calls its `visualizer_shapes::Color` counterpart,
+//! so a color written as text looks the same as one built by the producer.
+
 use nom::character::complete::*;
-use nom::combinator::*;
 use nom::IResult;
 use nom::bytes::complete::*;
 use nom::sequence::*;
 use nom::number::complete::*;
-use nom::multi::*;
+use nom::multi::separated_list1;
+use nom::combinator::{ map, map_opt, verify };
 use nom::branch::alt;
 use eframe::egui::*;
+use visualizer_shapes::{ Color, Colormap };
+
+fn args(s: &str) -> IResult<&str, Vec<f32>> {
+    delimited(
+        pair(tag("("), space0),
+        separated_list1(tuple((space0, tag(","), space0)), float),
+        pair(space0, tag(")")),
+    )(s)
+}
 
-const PHI_INV: f32 = 0.618033988749895;
+fn func<'a>(name: &'a str, arity: usize, mut f: impl FnMut(&[f32]) -> Color) -> impl FnMut(&'a str) -> IResult<&'a str, Color> {
+    move |s| {
+        let (s, (_, _, a)) = tuple((tag(name), space0, verify(args, |a: &[f32]| a.len() == arity)))(s)?;
+        Ok((s, f(&a)))
+    }
+}
 
 // https://martin.ankerl.com/2009/12/09/how-to-create-random-colors-programmatically/
-pub fn parse_tag_color(s: &str) -> IResult<&str, Color32> {
-    let (ss, (_, _, _, _, idx, _, _)) = tuple((
+pub fn parse_tag_color(s: &str) -> IResult<&str, Color> {
+    let (s, (_, _, _, _, idx, _, _)) = tuple((
             tag("tag"), space0, tag("("), space0,
             nom::character::complete::u64,
             space0, tag(")")
     ))(s)?;
-    let h = PHI_INV * idx as f32 * 17.0;
-    let s = PHI_INV * idx as f32 * 11.0;
-    let h = h - h.floor();
-    let s = s - s.floor();
-    let [r, g, b] = rgb_from_hsv((h, s * (1.0 - 0.25) + 0.25, 0.95));
-    Ok((ss, Rgba::from_rgb(r, g, b).into()))
+    Ok((s, Color::tag(idx as usize)))
 }
 
-pub fn parse_none_color(s: &str) -> IResult<&str, Color32> {
+pub fn parse_none_color(s: &str) -> IResult<&str, Color> {
     let (s, _) = tag("none()")(s)?;
-    Ok((s, Color32::TRANSPARENT))
+    Ok((s, Color::newa(0, 0, 0, 0)))
 }
 
-pub fn parse_named_color(s: &str) -> IResult<&str, Color32> {
-    let (s, (_, n, _)) = tuple((tag("named("), alpha1, tag(")")))(s)?;
-    let [r, g, b] = color_name::Color::val().by_string(n.to_owned()).unwrap_or([0, 0, 0]);
-    Ok((s, Color32::from_rgb(r, g, b)))
+/// Unknown names are black.
+pub fn parse_named_color(s: &str) -> IResult<&str, Color> {
+    map(delimited(tag("named("), alpha1, tag(")")), |n| Color::named(n).unwrap_or(Color::new(0, 0, 0)))(s)
 }
 
-pub fn parse_func_color<'a>(name: &'a str, mut func: impl FnMut(f32) -> (u8, u8, u8)) -> impl FnMut(&'a str) -> IResult<&'a str, Color32> {
-    move |s| {
-        let (s, (_, _, _, t, _, _)) = tuple(( tag(name), tag("("), space0, float, space0, tag(")") ))(s)?;
-        let (r, g, b) = func(t);
-        Ok((s, Color32::from_rgb(r, g, b)))
+/// `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+pub fn parse_hex_color(s: &str) -> IResult<&str, Color> {
+    map_opt(preceded(tag("#"), hex_digit1), Color::from_hex)(s)
+}
+
+pub fn parse_component_color(s: &str) -> IResult<&str, Color> {
+    alt((
+        func("rgba", 4, |a| Color::newa(a[0] as u8, a[1] as u8, a[2] as u8, a[3] as u8)),
+        func("rgb", 3, |a| Color::new(a[0] as u8, a[1] as u8, a[2] as u8)),
+        func("ratioa", 4, |a| Color::ratioa(a[0], a[1], a[2], a[3])),
+        func("ratio", 3, |a| Color::ratio(a[0], a[1], a[2])),
+        func("hsva", 4, |a| Color::hsva(a[0], a[1], a[2], a[3])),
+        func("hsv", 3, |a| Color::hsv(a[0], a[1], a[2])),
+        func("hsla", 4, |a| Color::hsla(a[0], a[1], a[2], a[3])),
+        func("hsl", 3, |a| Color::hsl(a[0], a[1], a[2])),
+    ))(s)
+}
+
+/// `viridis(t)` samples a colormap, `viridis(i, n)` takes the `i`-th of `n` colors.
+/// `tableau10(i)` takes the `i`-th category.
+pub fn parse_colormap_color(s: &str) -> IResult<&str, Color> {
+    let (s, (map, _, a)) = tuple((map_opt(alphanumeric1, Colormap::from_name), space0, args))(s)?;
+    match a[..] {
+        [i] if map.is_categorical() => Ok((s, map.eval_discrete(i as usize, 10))),
+        [t] => Ok((s, map.eval(t))),
+        [i, n] => Ok((s, map.eval_discrete(i as usize, n as usize))),
+        _ => Err(nom::Err::Error(nom::error::Error::new(s, nom::error::ErrorKind::Verify))),
     }
 }
 
-pub fn parse_color(s: &str) -> IResult<&str, Color32> {
-    alt((parse_tag_color, parse_none_color, parse_named_color, 
-         parse_func_color("turbo", |t| colorous::TURBO.eval_continuous(t as f64).as_tuple() )
+/// `lerp(c1, c2, t)`, `blend(below, top)` and `alpha(c, a)`.
+pub fn parse_mix_color(s: &str) -> IResult<&str, Color> {
+    let comma = |s| tuple((space0, tag(","), space0))(s);
+    alt((
+        map(
+            delimited(
+                pair(tag("lerp("), space0),
+                tuple((parse_shapes_color, comma, parse_shapes_color, comma, float)),
+                pair(space0, tag(")")),
+            ),
+            |(c1, _, c2, _, t)| c1.lerp(c2, t),
+        ),
+        map(
+            delimited(
+                pair(tag("blend("), space0),
+                tuple((parse_shapes_color, comma, parse_shapes_color)),
+                pair(space0, tag(")")),
+            ),
+            |(c1, _, c2)| c1.blend(c2),
+        ),
+        map(
+            delimited(
+                pair(tag("alpha("), space0),
+                tuple((parse_shapes_color, comma, nom::character::complete::u8)),
+                pair(space0, tag(")")),
+            ),
+            |(c, _, a)| c.alpha(a),
+        ),
+    ))(s)
+}
+
+pub fn parse_shapes_color(s: &str) -> IResult<&str, Color> {
+    alt((
+        parse_tag_color, parse_none_color, parse_named_color, parse_hex_color,
+        parse_component_color, parse_mix_color, parse_colormap_color,
     ))(s)
 }
+
+pub fn parse_color(s: &str) -> IResult<&str, Color32> {
+    let (s, c) = parse_shapes_color(s)?;
+    Ok((s, Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The color, if all of `s` is one.
+    fn color(s: &str) -> Option<Color> {
+        match parse_shapes_color(s) {
+            Ok(("", c)) => Some(c),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(color("#f00"), Some(Color::new(255, 0, 0)));
+        assert_eq!(color("#f008"), Some(Color::newa(255, 0, 0, 136)));
+        assert_eq!(color("#0080ff"), Some(Color::new(0, 128, 255)));
+        assert_eq!(color("#0080ff40"), Some(Color::newa(0, 128, 255, 64)));
+    }
+
+    #[test]
+    fn malformed_hex_colors() {
+        for s in ["#", "#f", "#ff", "#12345", "#1234567", "#123456789", "#ggg", "f00"] {
+            assert_eq!(color(s), None, "{}", s);
+        }
+        assert_eq!(color("#ff00zz"), None);
+    }
+
+    #[test]
+    fn component_colors() {
+        assert_eq!(color("rgb(1, 2, 3)"), Some(Color::new(1, 2, 3)));
+        assert_eq!(color("rgba(1,2,3,4)"), Some(Color::newa(1, 2, 3, 4)));
+        assert_eq!(color("ratio(1, 0, 0)"), Some(Color::ratio(1.0, 0.0, 0.0)));
+        assert_eq!(color("hsv(0, 1, 1)"), Some(Color::new(255, 0, 0)));
+        assert_eq!(color("hsva(0.5, 1, 1, 0.5)"), Some(Color::hsva(0.5, 1.0, 1.0, 0.5)));
+        assert_eq!(color("hsl(0, 1, 0.5)"), Some(Color::hsl(0.0, 1.0, 0.5)));
+    }
+
+    #[test]
+    fn malformed_component_colors() {
+        for s in ["hsv(0, 1)", "hsv(0, 1, 1, 1)", "hsv()", "hsv(a, 1, 1)", "hsv(0, 1, 1", "hsv 0, 1, 1", "rgb(1, 2,, 3)"] {
+            assert_eq!(color(s), None, "{}", s);
+        }
+    }
+
+    #[test]
+    fn named_tag_and_none_colors() {
+        assert_eq!(color("named(teal)"), Color::named("teal"));
+        assert_eq!(color("named(nosuchcolor)"), Some(Color::new(0, 0, 0)));
+        assert_eq!(color("tag(3)"), Some(Color::tag(3)));
+        assert_eq!(color("tag (3)"), Some(Color::tag(3)));
+        assert_eq!(color("tag( 3 )"), Some(Color::tag(3)));
+        assert_eq!(color("none()").map(|c| c.a), Some(0));
+    }
+
+    #[test]
+    fn tags_take_an_integer() {
+        for s in ["tag(1.7)", "tag(-1)", "tag()", "tag(x)"] {
+            assert_eq!(color(s), None, "{}", s);
+        }
+    }
+
+    #[test]
+    fn functions_allow_a_space_before_the_arguments() {
+        assert_eq!(color("rgb (1, 2, 3)"), Some(Color::new(1, 2, 3)));
+        assert_eq!(color("turbo (0.5)"), Some(Colormap::Turbo.eval(0.5)));
+    }
+
+    #[test]
+    fn colormap_colors() {
+        assert_eq!(color("viridis(0.5)"), Some(Colormap::Viridis.eval(0.5)));
+        assert_eq!(color("viridis(2, 5)"), Some(Colormap::Viridis.eval_discrete(2, 5)));
+        assert_eq!(color("viridis(1, 2, 3)"), None);
+        assert_eq!(color("nosuchmap(0.5)"), None);
+    }
+
+    #[test]
+    fn mixed_colors() {
+        let (red, blue) = (Color::new(255, 0, 0), Color::new(0, 0, 255));
+        assert_eq!(color("lerp(#f00, #00f, 0.5)"), Some(red.lerp(blue, 0.5)));
+        assert_eq!(color("blend(#f00, alpha(#00f, 128))"), Some(red.blend(blue.alpha(128))));
+        assert_eq!(color("alpha(#f00, 300)"), None);
+    }
+}
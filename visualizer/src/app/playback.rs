@@ -0,0 +1,224 @@
+use eframe::egui::*;
+use serde::{ Serialize, Deserialize };
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Repeat {
+    Once,
+    Loop,
+    PingPong,
+}
+
+impl Repeat {
+    fn label(self) -> &'static str {
+        match self {
+            Repeat::Once => "once",
+            Repeat::Loop => "loop",
+            Repeat::PingPong => "ping-pong",
+        }
+    }
+}
+
+/// Steps through the frames on a timer. The timer is the egui input time and the next step
+/// is scheduled with `request_repaint_after`, so it runs the same on native and on the web.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Playback {
+    #[serde(skip)]
+    pub playing: bool,
+    pub fps: f32,
+    pub step: usize,
+    pub repeat: Repeat,
+    /// Pauses on the next keyframe.
+    pub until_keyframe: bool,
+    #[serde(skip)]
+    backward: bool,
+    #[serde(skip)]
+    last_tick: Option<f64>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            fps: 10.0,
+            step: 1,
+            repeat: Repeat::Once,
+            until_keyframe: false,
+            backward: false,
+            last_tick: None,
+        }
+    }
+}
+
+impl Playback {
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+        self.last_tick = None;
+    }
+
+    /// `count` is the number of frames, which bounds the step.
+    pub fn ui(&mut self, ui: &mut Ui, count: usize) {
+        ui.horizontal(|ui| {
+            if ui.button(if self.playing { "pause" } else { "play" }).clicked() {
+                self.toggle();
+            }
+            ui.add(DragValue::new(&mut self.fps).range(0.1..=240.0).speed(0.1).suffix(" fps"));
+            ui.add(DragValue::new(&mut self.step).range(1..=count.max(1)).prefix("step "));
+        });
+        ui.horizontal(|ui| {
+            ComboBox::from_id_salt("repeat")
+                .selected_text(self.repeat.label())
+                .show_ui(ui, |ui| {
+                    for repeat in [Repeat::Once, Repeat::Loop, Repeat::PingPong] {
+                        ui.selectable_value(&mut self.repeat, repeat, repeat.label());
+                    }
+                });
+            ui.checkbox(&mut self.until_keyframe, "stop at keyframes");
+        });
+    }
+
+    /// The frame after `idx`, or `None` when playback ends.
+    fn next(&mut self, idx: usize, count: usize) -> Option<usize> {
+        let last = count.checked_sub(1)?;
+        let step = self.step.max(1);
+        if self.backward {
+            match idx.checked_sub(step) {
+                Some(i) => Some(i),
+                None if self.repeat == Repeat::PingPong => {
+                    self.backward = false;
+                    Some((step - idx).min(last))
+                }
+                None => None,
+            }
+        }
+        else {
+            match idx.checked_add(step) {
+                Some(i) if i <= last => Some(i),
+                _ => match self.repeat {
+                    Repeat::Once => if idx < last { Some(last) } else { None },
+                    Repeat::Loop => Some(0),
+                    Repeat::PingPong => {
+                        self.backward = true;
+                        Some(last.saturating_sub(idx.saturating_add(step) - last))
+                    }
+                },
+            }
+        }
+    }
+
+    /// The frames passed moving from `idx` to `next`, in order, each with the direction
+    /// playback goes on in from there.
+    fn passed(&self, idx: usize, next: usize, last: usize, was_backward: bool) -> Vec<(usize, bool)> {
+        let (b0, b1) = (was_backward, self.backward);
+        match (b0, b1) {
+            (false, false) if next > idx => (idx + 1..=next).map(|i| (i, b1)).collect(),
+            // looped around
+            (false, false) => (idx + 1..=last).chain(0..=next).map(|i| (i, b1)).collect(),
+            (true, true) => (next..idx).rev().map(|i| (i, b1)).collect(),
+            // bounced off the last frame
+            (false, true) => (idx + 1..=last).map(|i| (i, b0)).chain((next..last).rev().map(|i| (i, b1))).collect(),
+            // bounced off the first frame
+            (true, false) => (0..idx).rev().map(|i| (i, b0)).chain((1..=next).map(|i| (i, b1))).collect(),
+        }
+    }
+
+    /// Moves `frame_idx` on when a step is due and schedules the next one.
+    pub fn tick(&mut self, ctx: &Context, frame_idx: &mut usize, count: usize, is_keyframe: impl Fn(usize) -> bool) {
+        if !self.playing {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        let interval = 1.0 / self.fps.max(0.1) as f64;
+        // playing once from the last frame starts over
+        if self.last_tick.is_none() && self.repeat == Repeat::Once && *frame_idx + 1 >= count {
+            *frame_idx = 0;
+        }
+        let last_tick = *self.last_tick.get_or_insert(now);
+        if now - last_tick >= interval {
+            let was_backward = self.backward;
+            match self.next(*frame_idx, count) {
+                Some(i) => {
+                    // stops at the first keyframe on the way, even one the step skips over
+                    let keyframe = if self.until_keyframe {
+                        self.passed(*frame_idx, i, count - 1, was_backward).into_iter().find(|&(k, _)| is_keyframe(k))
+                    }
+                    else {
+                        None
+                    };
+                    match keyframe {
+                        Some((k, backward)) => {
+                            *frame_idx = k;
+                            self.backward = backward;
+                            self.playing = false;
+                        }
+                        None => *frame_idx = i,
+                    }
+                }
+                None => self.playing = false,
+            }
+            // catch up without bursting after a stall, e.g. a hidden browser tab
+            self.last_tick = Some(if now - last_tick < 2.0 * interval { last_tick + interval } else { now });
+        }
+        if self.playing {
+            let wait = (self.last_tick.unwrap_or(now) + interval - now).max(0.0);
+            ctx.request_repaint_after(Duration::from_secs_f64(wait));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playback(step: usize, repeat: Repeat) -> Playback {
+        Playback { step, repeat, ..Playback::default() }
+    }
+
+    /// The frames visited from `idx` on, at most `n` of them.
+    fn run(p: &mut Playback, mut idx: usize, count: usize, n: usize) -> Vec<usize> {
+        let mut res = vec![];
+        while let Some(i) = p.next(idx, count).filter(|_| res.len() < n) {
+            res.push(i);
+            idx = i;
+        }
+        res
+    }
+
+    #[test]
+    fn once_stops_at_the_last_frame() {
+        assert_eq!(run(&mut playback(3, Repeat::Once), 0, 8, 10), vec![3, 6, 7]);
+    }
+
+    #[test]
+    fn loop_and_ping_pong_turn_around() {
+        assert_eq!(run(&mut playback(3, Repeat::Loop), 0, 8, 5), vec![3, 6, 0, 3, 6]);
+        assert_eq!(run(&mut playback(3, Repeat::PingPong), 0, 8, 6), vec![3, 6, 5, 2, 1, 4]);
+    }
+
+    #[test]
+    fn huge_steps_do_not_overflow() {
+        assert_eq!(run(&mut playback(usize::MAX, Repeat::Once), 2, 8, 10), vec![7]);
+        assert_eq!(run(&mut playback(usize::MAX, Repeat::Loop), 2, 8, 2), vec![0, 0]);
+        assert_eq!(run(&mut playback(usize::MAX, Repeat::PingPong), 2, 8, 2), vec![0, 7]);
+    }
+
+    #[test]
+    fn step_zero_moves_on() {
+        assert_eq!(run(&mut playback(0, Repeat::Once), 0, 3, 10), vec![1, 2]);
+    }
+
+    #[test]
+    fn the_frames_passed_include_the_ones_skipped() {
+        let mut p = playback(3, Repeat::PingPong);
+        let frames = |v: &[(usize, bool)]| v.iter().map(|f| f.0).collect::<Vec<_>>();
+        assert_eq!(frames(&p.passed(0, 3, 7, false)), vec![1, 2, 3]);
+        assert_eq!(p.next(6, 8), Some(5));
+        let passed = p.passed(6, 5, 7, false);
+        assert_eq!(passed, vec![(7, false), (6, true), (5, true)]);
+        assert_eq!(p.next(1, 8), Some(2));
+        assert_eq!(frames(&p.passed(1, 2, 7, true)), vec![0, 1, 2]);
+        let p = playback(3, Repeat::Loop);
+        assert_eq!(frames(&p.passed(6, 0, 7, false)), vec![7, 0]);
+    }
+}
@@ -0,0 +1,116 @@
+use eframe::egui::*;
+
+use super::parser::PaintTrack;
+
+/// Lists no more hits than this, so a query matching everything stays responsive.
+const MAX_HITS: usize = 1000;
+
+/// An element whose hover msg matched.
+pub struct Hit {
+    pub track: usize,
+    pub frame: usize,
+    pub elem: usize,
+    /// The producer's element index.
+    source_index: usize,
+    msg: String,
+}
+
+enum Matcher {
+    Substring(String),
+    Regex(regex::Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, msg: &str) -> bool {
+        match self {
+            Matcher::Substring(s) => msg.contains(s.as_str()),
+            Matcher::Regex(re) => re.is_match(msg),
+        }
+    }
+}
+
+/// Searches the hover msgs of every frame of every track.
+#[derive(Default)]
+pub struct Search {
+    pub query: String,
+    pub regex: bool,
+    hits: Vec<Hit>,
+    /// The number of matches, including the ones past `MAX_HITS`.
+    total: usize,
+    status: String,
+}
+
+impl Search {
+    /// Forgets the hits, whose indices are only valid for the data they were found in.
+    pub fn clear(&mut self) {
+        self.hits.clear();
+        self.total = 0;
+        self.status.clear();
+    }
+
+    fn run(&mut self, tracks: &[PaintTrack]) {
+        self.clear();
+        let matcher = if self.regex {
+            match regex::Regex::new(&self.query) {
+                Ok(re) => Matcher::Regex(re),
+                Err(e) => {
+                    self.status = e.to_string();
+                    return;
+                }
+            }
+        }
+        else {
+            Matcher::Substring(self.query.clone())
+        };
+        for (t, track) in tracks.iter().enumerate() {
+            for (f, frame) in track.frames.iter().enumerate() {
+                for (e, elem) in frame.elems.iter().enumerate() {
+                    let Some(msg) = elem.msg() else { continue };
+                    if !matcher.is_match(msg) {
+                        continue;
+                    }
+                    self.total += 1;
+                    if self.hits.len() < MAX_HITS {
+                        self.hits.push(Hit { track: t, frame: f, elem: e, source_index: elem.source_index, msg: msg.to_owned() });
+                    }
+                }
+            }
+        }
+        self.status = match self.total {
+            0 => "no match".to_owned(),
+            n if n > self.hits.len() => format!("{} matches, showing the first {}", n, self.hits.len()),
+            n => format!("{} matches", n),
+        };
+    }
+
+    /// Returns the hit clicked in the list.
+    pub fn ui(&mut self, ui: &mut Ui, tracks: &[PaintTrack]) -> Option<&Hit> {
+        ui.horizontal(|ui| {
+            let edit = ui.add(TextEdit::singleline(&mut self.query).hint_text("msg contains").desired_width(140.0));
+            ui.checkbox(&mut self.regex, "regex");
+            let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            if (ui.button("search").clicked() || enter) && !self.query.is_empty() {
+                self.run(tracks);
+            }
+        });
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+        let row_height = ui.text_style_height(&TextStyle::Body);
+        let mut clicked = None;
+        ScrollArea::vertical().id_salt("search hits").max_height(240.0).show_rows(ui, row_height, self.hits.len(), |ui, range| {
+            for i in range {
+                let hit = &self.hits[i];
+                let track = match tracks.get(hit.track) {
+                    Some(t) if tracks.len() > 1 => format!("{} ", t.label()),
+                    _ => String::new(),
+                };
+                let line = hit.msg.lines().next().unwrap_or("");
+                if ui.link(format!("{}{}#{}: {}", track, hit.frame, hit.source_index, line)).on_hover_text(&hit.msg).clicked() {
+                    clicked = Some(i);
+                }
+            }
+        });
+        clicked.map(|i| &self.hits[i])
+    }
+}
@@ -0,0 +1,130 @@
+use eframe::egui::Rect;
+use serde::{ Serialize, Deserialize };
+use std::path::{ Path, PathBuf };
+
+use super::bookmarks::Bookmark;
+use super::coloring::ColorBy;
+use super::filter::Filter;
+use super::playback::Playback;
+
+const MAX_RECENT: usize = 10;
+
+/// The extension of session files saved to share a view.
+pub const EXTENSION: &str = "visession";
+
+/// What the viewer restores on the next start, and what a `.visession` file shares.
+/// Egui keeps the panel sizes and the open sections in its own memory, which eframe saves alongside.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    /// The loaded file, if it came from a path.
+    pub path: Option<PathBuf>,
+    /// Most recent first. Left out of session files.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recent: Vec<PathBuf>,
+    /// The name of the selected track, `None` for the main track.
+    pub track: Option<String>,
+    pub frame_idx: usize,
+    pub view: Option<Rect>,
+    pub side_by_side: bool,
+    pub watch: bool,
+    pub playback: Playback,
+    pub color_by: ColorBy,
+    pub filter: Filter,
+    pub show_all_hits: bool,
+    pub live_address: Option<String>,
+    pub live_follow: bool,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Session {
+    /// Makes the data path relative to `dir`, where the session file goes, so that
+    /// the two can be shared together.
+    pub fn relative_to(mut self, dir: &Path) -> Self {
+        let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_owned());
+        let path = self.path.as_ref().and_then(|p| std::fs::canonicalize(p).ok());
+        if let Some(rel) = path.as_ref().and_then(|p| p.strip_prefix(&dir).ok()) {
+            self.path = Some(rel.to_owned());
+        }
+        self
+    }
+
+    /// Resolves a data path relative to `dir`, where the session file was read from.
+    pub fn resolve(mut self, dir: &Path) -> Self {
+        if let Some(ref path) = self.path {
+            if path.is_relative() {
+                self.path = Some(dir.join(path));
+            }
+        }
+        self
+    }
+
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        Ok(ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)
+    }
+
+    pub fn from_ron(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(ron::de::from_bytes(bytes)?)
+    }
+}
+
+/// Moves `path` to the front of `recent`.
+pub fn add_recent(recent: &mut Vec<PathBuf>, path: PathBuf) {
+    recent.retain(|p| *p != path);
+    recent.insert(0, path);
+    recent.truncate(MAX_RECENT);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("visualizer-session-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn paths_next_to_the_session_round_trip() {
+        let dir = temp_dir("inside");
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        let file = dir.join("data").join("run.bin");
+        std::fs::write(&file, b"").unwrap();
+        let session = Session { path: Some(file.clone()), ..Default::default() }.relative_to(&dir);
+        assert_eq!(session.path.as_deref(), Some(Path::new("data/run.bin")));
+        let session = session.resolve(&dir);
+        assert_eq!(
+            session.path.map(|p| std::fs::canonicalize(p).unwrap()),
+            Some(std::fs::canonicalize(&file).unwrap()),
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn paths_elsewhere_stay_absolute() {
+        let dir = temp_dir("session");
+        let other = temp_dir("other");
+        let file = other.join("run.bin");
+        std::fs::write(&file, b"").unwrap();
+        let session = Session { path: Some(file.clone()), ..Default::default() }.relative_to(&dir);
+        assert_eq!(session.path.as_deref(), Some(file.as_path()));
+        let session = session.resolve(&dir);
+        assert_eq!(session.path, Some(file));
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&other).unwrap();
+    }
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let session = Session::from_ron(b"(frame_idx: 3, side_by_side: true)").unwrap();
+        assert_eq!(session.frame_idx, 3);
+        assert!(session.side_by_side);
+        assert_eq!(session.path, None);
+        assert!(session.recent.is_empty());
+        assert!(session.bookmarks.is_empty());
+        assert!(!session.watch);
+        let session = Session::from_ron(b"()").unwrap();
+        assert_eq!(session.frame_idx, 0);
+    }
+}
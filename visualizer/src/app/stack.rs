@@ -0,0 +1,68 @@
+use eframe::egui::*;
+
+use super::parser::PaintFrame;
+
+/// Lists every element under the pointer instead of the topmost one, with one of them
+/// active: outlined, and selected by a click.
+#[derive(Default)]
+pub struct Stack {
+    pub enabled: bool,
+    /// The track and frame `hits` index into.
+    at: (usize, usize),
+    /// Element indices, topmost first.
+    hits: Vec<usize>,
+    active: usize,
+}
+
+impl Stack {
+    /// Sets the elements under the pointer, keeping the active one while they stay the same.
+    pub fn update(&mut self, at: (usize, usize), hits: Vec<usize>) {
+        if at != self.at || hits != self.hits {
+            self.at = at;
+            self.hits = hits;
+            self.active = 0;
+        }
+    }
+
+    /// Cycles the active element with C, or with the scroll wheel while alt is held.
+    pub fn handle_input(&mut self, ctx: &Context) {
+        let n = self.hits.len();
+        if n < 2 {
+            return;
+        }
+        let (key, scroll) = ctx.input(|i| (i.key_pressed(Key::C), if i.modifiers.alt { i.raw_scroll_delta.y } else { 0.0 }));
+        if (key && !ctx.wants_keyboard_input()) || scroll < 0.0 {
+            self.active = (self.active + 1) % n;
+        }
+        else if scroll > 0.0 {
+            self.active = (self.active + n - 1) % n;
+        }
+    }
+
+    pub fn active(&self) -> Option<usize> {
+        self.hits.get(self.active).copied()
+    }
+
+    /// Lists the hits top to bottom with their shape, color and msg.
+    pub fn tooltip(&self, ui: &mut Ui, frame: &PaintFrame) {
+        for (k, &i) in self.hits.iter().enumerate() {
+            let Some(elem) = frame.elems.get(i) else { continue };
+            ui.horizontal(|ui| {
+                let (rect, _) = ui.allocate_exact_size(vec2(12.0, 12.0), Sense::hover());
+                if let Some(c) = elem.colors().into_iter().flatten().next() {
+                    ui.painter().rect_filled(rect, 2.0, c);
+                }
+                let text = format!("{} #{}: {}", elem.kind, i, elem.msg().unwrap_or(""));
+                if k == self.active {
+                    ui.label(RichText::new(text).strong());
+                }
+                else {
+                    ui.label(text);
+                }
+            });
+        }
+        if self.hits.len() > 1 {
+            ui.weak("C or alt + scroll to cycle, click to select");
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use eframe::egui::*;
+
+const EVENT_COLOR: Color32 = Color32::from_rgb(230, 120, 20);
+
+/// A labeled frame on the timeline: an event marked by the producer, or a user bookmark.
+pub struct Marker {
+    pub frame: usize,
+    pub label: String,
+    pub bookmark: bool,
+}
+
+/// Draws `markers` as ticks across a slider over the frames `0..=last`, naming the ones
+/// under the pointer.
+pub fn ticks(ui: &Ui, slider: &Response, last: usize, markers: &[Marker]) {
+    let rect = slider.rect;
+    // the handle center runs over the rect shrunk by the handle radius, as in `Slider::position_range`
+    let range = rect.x_range().shrink(rect.height() / 2.5);
+    let painter = ui.painter();
+    let pointer = slider.hover_pos();
+    let mut hovered = vec![];
+    for m in markers {
+        let x = if last == 0 { range.min } else { range.min + range.span() * m.frame as f32 / last as f32 };
+        let color = if m.bookmark { ui.visuals().hyperlink_color } else { EVENT_COLOR };
+        let stroke = Stroke::new(2.0, color);
+        // leave the rail in the middle visible
+        painter.line_segment([pos2(x, rect.top()), pos2(x, rect.top() + rect.height() * 0.3)], stroke);
+        painter.line_segment([pos2(x, rect.bottom() - rect.height() * 0.3), pos2(x, rect.bottom())], stroke);
+        if pointer.is_some_and(|p| (p.x - x).abs() <= 3.0) {
+            hovered.push(format!("{}: {}", m.frame, m.label));
+        }
+    }
+    if !hovered.is_empty() {
+        slider.clone().on_hover_text_at_pointer(hovered.join("\n"));
+    }
+}
+
+/// The frame of the nearest marker after `idx`, or before it unless `forward`.
+pub fn step(markers: &[Marker], idx: usize, forward: bool) -> Option<usize> {
+    if forward {
+        markers.iter().map(|m| m.frame).filter(|&f| f > idx).min()
+    }
+    else {
+        markers.iter().map(|m| m.frame).filter(|&f| f < idx).max()
+    }
+}
@@ -5,7 +5,7 @@ pub fn shape_transform(shape: Shape, to_screen: &RectTransform) -> Option<Shape>
     match shape {
         Shape::Path(path) => {
             Some(Shape::Path(PathShape {
-                points: path.points.iter().map(|p| to_screen * p.clone()).collect(),
+                points: path.points.iter().map(|p| to_screen * *p).collect(),
                 ..path
             }))
         }
@@ -0,0 +1,150 @@
+use eframe::egui::Context;
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, SystemTime };
+
+const POLL: Duration = Duration::from_millis(500);
+
+type Stamp = (SystemTime, u64);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// What a poll does with the file at `current`, given the stamp of the last loaded contents and
+/// the one of the previous poll.
+#[derive(Debug, PartialEq)]
+enum Change {
+    /// The loaded contents are current.
+    None,
+    /// It changed since the previous poll, so it may still be written to.
+    Settling,
+    /// It looked the same for two polls and is worth reading.
+    Settled,
+}
+
+fn change(seen: Option<Stamp>, pending: Option<Stamp>, current: Stamp) -> Change {
+    if Some(current) == seen {
+        Change::None
+    }
+    else if pending != Some(current) {
+        Change::Settling
+    }
+    else {
+        Change::Settled
+    }
+}
+
+/// Polls the loaded file for changes. Polling works on every platform and filesystem,
+/// and a file only counts as changed once it has looked the same for two polls, so a
+/// file still being written is not read halfway.
+#[derive(Default)]
+pub struct Watch {
+    pub enabled: bool,
+    path: Option<PathBuf>,
+    seen: Option<Stamp>,
+    pending: Option<Stamp>,
+    /// The stamp of the contents returned by the last `poll`, until they are `loaded`.
+    read: Option<Stamp>,
+    last_poll: f64,
+}
+
+impl Watch {
+    /// Watches `path` from its current state, or nothing for data that did not come from a file.
+    pub fn set_path(&mut self, path: Option<PathBuf>) {
+        self.seen = path.as_deref().and_then(stamp);
+        self.pending = None;
+        self.read = None;
+        self.path = path;
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The new contents of the file once it has changed and settled. Call `loaded` once they
+    /// are in; contents that fail to load are read again on a later poll.
+    pub fn poll(&mut self, ctx: &Context) -> Option<Vec<u8>> {
+        let path = self.path.as_deref().filter(|_| self.enabled)?;
+        ctx.request_repaint_after(POLL);
+        let now = ctx.input(|i| i.time);
+        if now - self.last_poll < POLL.as_secs_f64() {
+            return None;
+        }
+        self.last_poll = now;
+        let current = stamp(path)?;
+        match change(self.seen, self.pending, current) {
+            Change::None => {
+                self.pending = None;
+                None
+            }
+            Change::Settling => {
+                self.pending = Some(current);
+                None
+            }
+            Change::Settled => {
+                // settles again before the next read
+                self.pending = None;
+                self.read = Some(current);
+                std::fs::read(path).ok()
+            }
+        }
+    }
+
+    /// Marks the contents returned by the last `poll` as loaded.
+    pub fn loaded(&mut self) {
+        if let Some(read) = self.read.take() {
+            self.seen = Some(read);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64, len: u64) -> Stamp {
+        (SystemTime::UNIX_EPOCH + Duration::from_secs(secs), len)
+    }
+
+    /// The changes seen by successive polls of `stamps`, loading every settled one when `load` says so.
+    fn run(stamps: &[Stamp], load: impl Fn(Stamp) -> bool) -> Vec<Change> {
+        let (mut seen, mut pending) = (Some(stamps[0]), None);
+        stamps.iter().map(|&current| {
+            let c = change(seen, pending, current);
+            pending = if c == Change::Settling { Some(current) } else { None };
+            if c == Change::Settled && load(current) {
+                seen = Some(current);
+            }
+            c
+        }).collect()
+    }
+
+    #[test]
+    fn a_change_is_read_once_it_settles() {
+        let (a, b) = (at(1, 10), at(2, 20));
+        assert_eq!(run(&[a, b, b, b], |_| true), vec![Change::None, Change::Settling, Change::Settled, Change::None]);
+    }
+
+    #[test]
+    fn a_file_still_being_written_is_not_read() {
+        let stamps = [at(1, 10), at(2, 20), at(3, 30), at(4, 40), at(4, 40)];
+        assert_eq!(run(&stamps, |_| true), vec![Change::None, Change::Settling, Change::Settling, Change::Settling, Change::Settled]);
+    }
+
+    #[test]
+    fn a_failed_load_is_retried() {
+        let (a, b) = (at(1, 10), at(2, 20));
+        let failing = b;
+        assert_eq!(
+            run(&[a, b, b, b, b], |s| s != failing),
+            vec![Change::None, Change::Settling, Change::Settled, Change::Settling, Change::Settled],
+        );
+    }
+
+    #[test]
+    fn going_back_to_the_loaded_contents_is_no_change() {
+        let (a, b) = (at(1, 10), at(2, 20));
+        assert_eq!(run(&[a, b, a, a], |_| true), vec![Change::None, Change::Settling, Change::None, Change::None]);
+    }
+}
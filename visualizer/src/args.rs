@@ -0,0 +1,106 @@
+use anyhow::{ anyhow, bail, Context as _ };
+use std::path::PathBuf;
+
+pub const USAGE: &str = "usage: visualizer [FILE | -] [--frame N] [--view x0,y0,x1,y1] [--watch]";
+
+/// What to show on startup, from the command line.
+#[derive(Debug, Default, Clone)]
+pub struct Args {
+    /// The file to open, a `.visession` file to restore, or `-` for frames piped into stdin.
+    pub path: Option<PathBuf>,
+    pub frame: Option<usize>,
+    /// The world rect to show, `[x0, y0, x1, y1]` with `x0 < x1` and `y0 < y1`.
+    pub view: Option<[f32; 4]>,
+    /// Reloads the file when it changes.
+    pub watch: bool,
+    pub help: bool,
+}
+
+fn parse_view(s: &str) -> anyhow::Result<[f32; 4]> {
+    let v = s.split(',')
+        .map(|x| x.trim().parse::<f32>().with_context(|| format!("invalid number {:?} in --view", x)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    match v[..] {
+        // the corners may come in any order
+        [x0, y0, x1, y1] if v.iter().all(|x| x.is_finite()) && x0 != x1 && y0 != y1 => Ok([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]),
+        _ => bail!("--view takes four finite numbers x0,y0,x1,y1 spanning a non-empty rect"),
+    }
+}
+
+impl Args {
+    /// Parses the arguments after the program name. `--frame=N` and `--view=...` work too.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut res = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_owned(), Some(v.to_owned())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || inline.clone().or_else(|| args.next()).filter(|v| !v.is_empty()).ok_or_else(|| anyhow!("{} needs a value", flag));
+            match flag.as_str() {
+                "--frame" => {
+                    let v = value()?;
+                    res.frame = Some(v.parse().with_context(|| format!("invalid frame {:?}", v))?);
+                }
+                "--view" => res.view = Some(parse_view(&value()?)?),
+                "--watch" if inline.is_some() => bail!("--watch takes no value"),
+                "--watch" => res.watch = true,
+                "-h" | "--help" => res.help = true,
+                _ if flag.starts_with("--") => bail!("unknown option {}", flag),
+                _ if res.path.is_none() => res.path = Some(PathBuf::from(arg)),
+                _ => bail!("unexpected argument {}", arg),
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Args> {
+        Args::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let args = parse(&["run.vis", "--frame", "3", "--view=0,1,2,3", "--watch"]).unwrap();
+        assert_eq!(args.path, Some(PathBuf::from("run.vis")));
+        assert_eq!(args.frame, Some(3));
+        assert_eq!(args.view, Some([0.0, 1.0, 2.0, 3.0]));
+        assert!(args.watch && !args.help);
+        assert!(parse(&["-h"]).unwrap().help);
+        assert_eq!(parse(&["--frame=7", "-"]).unwrap().path, Some(PathBuf::from("-")));
+    }
+
+    #[test]
+    fn options_need_their_values() {
+        for args in [&["--frame"][..], &["--frame="], &["--view"], &["--view="]] {
+            assert!(parse(args).unwrap_err().to_string().contains("needs a value"), "{:?}", args);
+        }
+        assert!(parse(&["--frame", "x"]).is_err());
+        assert!(parse(&["--frame=-1"]).is_err());
+        assert!(parse(&["--watch=yes"]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_and_extra_arguments() {
+        assert!(parse(&["--nope"]).is_err());
+        assert!(parse(&["a.vis", "b.vis"]).is_err());
+    }
+
+    #[test]
+    fn views_are_normalized() {
+        assert_eq!(parse_view("2, 3, 0, 1").unwrap(), [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(parse_view("0,3,2,1").unwrap(), [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn rejects_malformed_views() {
+        for s in ["0,0,1", "0,0,1,1,1", "0,0,0,1", "0,1,1,1", "0,0,1,inf", "0,0,1,NaN", "0,0,1,x", "0,,1,1"] {
+            assert!(parse_view(s).is_err(), "{}", s);
+        }
+    }
+}
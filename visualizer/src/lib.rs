@@ -2,3 +2,5 @@
 
 mod app;
 pub use app::EguiSample;
+mod args;
+pub use args::{ Args, USAGE };
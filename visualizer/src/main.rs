@@ -6,6 +6,18 @@
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    let args = match visualizer::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, visualizer::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", visualizer::USAGE);
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
@@ -20,7 +32,7 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "eframe template",
         native_options,
-        Box::new(|cc| Ok(Box::new(visualizer::EguiSample::new(cc)))),
+        Box::new(|cc| Ok(Box::new(visualizer::EguiSample::with_args(cc, args)))),
     )
 }
 